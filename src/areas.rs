@@ -1,3 +1,7 @@
+use crate::astro;
+use crate::location::Location;
+use std::collections::BTreeMap;
+
 #[allow(clippy::approx_constant)]
 pub const AREAS: [&[f64]; 30] = [
     &[
@@ -174,6 +178,124 @@ pub const AREAS: [&[f64]; 30] = [
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Area(pub usize);
 
+// Areas whose centre is at least this high are recommended for counting.
+pub const RECOMMENDED_ALTITUDE: f64 = 50.0;
+
+// The centres of the areas bundled with Meteoraid, in the layout that `AreaCentres::parse`
+// reads. Areas that are not listed are not checked.
+static BUNDLED_AREA_CENTRES: &str = include_str!("../data/area_centres.csv");
+
+// Where the centres of the areas are, as right ascension and declination in degrees, with
+// the same layout as the shower catalog: a header line, then "AREA;RA;DEC" lines.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct AreaCentres(BTreeMap<usize, (f64, f64)>);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AreaAdvice {
+    Recommended,
+    Usable,
+    // Below the minimum altitude, or set.
+    Unusable,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AreaAltitude {
+    pub area: Area,
+    pub altitude: f64,
+    pub advice: AreaAdvice,
+}
+
+impl AreaCentres {
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_AREA_CENTRES)
+            .expect("[supposedly unreachable] Invalid bundled area centres")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn parse(data: &str) -> Result<Self, AreaCentresError> {
+        let mut centres = BTreeMap::new();
+        for (num, line) in data.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: &str| AreaCentresError {
+                line: num + 1,
+                message: message.to_owned(),
+            };
+
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            if fields.len() != 3 {
+                return Err(error("Expected 3 fields."));
+            }
+            let area: usize = fields[0].parse().map_err(|_| error("Invalid area."))?;
+            if area == 0 || area > AREAS.len() {
+                return Err(error("Areas are numbered from 1 to 30."));
+            }
+            let ra: f64 = fields[1].parse().map_err(|_| error("Invalid RA."))?;
+            if !(0.0..360.0).contains(&ra) {
+                return Err(error("RA must be between 0 and 360."));
+            }
+            let dec: f64 = fields[2].parse().map_err(|_| error("Invalid Dec."))?;
+            if !(-90.0..=90.0).contains(&dec) {
+                return Err(error("Dec must be between -90 and 90."));
+            }
+            if centres.insert(area, (ra, dec)).is_some() {
+                return Err(error("Area given twice."));
+            }
+        }
+        Ok(Self(centres))
+    }
+
+    pub fn altitude(&self, area: Area, location: Location, jd: f64) -> Option<f64> {
+        self.0
+            .get(&area.0)
+            .map(|&(ra, dec)| astro::altitude(ra, dec, location.latitude, location.longitude, jd))
+    }
+
+    // The altitude of every area with a known centre, highest first.
+    pub fn altitudes(&self, location: Location, jd: f64, min_altitude: f64) -> Vec<AreaAltitude> {
+        let mut altitudes: Vec<AreaAltitude> = self
+            .0
+            .keys()
+            .map(|&number| {
+                let area = Area(number);
+                let altitude = self.altitude(area, location, jd).unwrap();
+                let advice = if altitude < min_altitude {
+                    AreaAdvice::Unusable
+                } else if altitude >= RECOMMENDED_ALTITUDE {
+                    AreaAdvice::Recommended
+                } else {
+                    AreaAdvice::Usable
+                };
+                AreaAltitude {
+                    area,
+                    altitude,
+                    advice,
+                }
+            })
+            .collect();
+        altitudes.sort_by(|a, b| b.altitude.partial_cmp(&a.altitude).unwrap());
+        altitudes
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AreaCentresError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for AreaCentresError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AreaCentresError {}
+
 pub fn get_limiting_magnitude(stars: usize, area: Area) -> Option<f64> {
     let Area(area_number) = area;
     AREAS
@@ -181,42 +303,182 @@ pub fn get_limiting_magnitude(stars: usize, area: Area) -> Option<f64> {
         .and_then(|a| a.get(stars - 1).copied())
 }
 
-pub fn get_limiting_magnitude_avg(counts: &[(usize, Area)]) -> Option<f64> {
-    let mut lms: Vec<f64> = counts
+// How an area was used for the average limiting magnitude.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AreaVerdict {
+    Kept,
+    // Its limiting magnitude lies in a gap wider than 0.3 mag, so it is left out.
+    InGap,
+    // It lies in such a gap, but so do all other areas, so all of them are averaged.
+    Fallback,
+    // Its centre is below the horizon, so it cannot have been counted.
+    BelowHorizon,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AreaLm {
+    pub stars: usize,
+    pub area: Area,
+    // From the table, which is for an area near the zenith.
+    pub limiting_magnitude: f64,
+    // Added for the extinction at the altitude of the area, if it is corrected.
+    pub correction: f64,
+    pub verdict: AreaVerdict,
+}
+
+impl AreaLm {
+    pub fn corrected(&self) -> f64 {
+        self.limiting_magnitude + self.correction
+    }
+
+    pub fn is_kept(&self) -> bool {
+        match self.verdict {
+            AreaVerdict::Kept | AreaVerdict::Fallback => true,
+            AreaVerdict::InGap | AreaVerdict::BelowHorizon => false,
+        }
+    }
+}
+
+// The limiting magnitude of every area of one count, in the order they were given, and
+// the average of the ones that were kept.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LmExplanation {
+    pub areas: Vec<AreaLm>,
+    pub average: f64,
+}
+
+impl LmExplanation {
+    pub fn is_fallback(&self) -> bool {
+        self.areas
+            .iter()
+            .any(|a| a.verdict == AreaVerdict::Fallback)
+    }
+}
+
+impl std::fmt::Display for LmExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for area in &self.areas {
+            let lm = if area.correction == 0_f64 {
+                format!("{:.2}", area.limiting_magnitude)
+            } else {
+                format!(
+                    "{:.2}, corrected {:.2}",
+                    area.limiting_magnitude,
+                    area.corrected()
+                )
+            };
+            writeln!(
+                f,
+                "area {:>2}, {:>3} stars: Lm {}, {}",
+                area.area.0,
+                area.stars,
+                lm,
+                match area.verdict {
+                    AreaVerdict::Kept => "kept",
+                    AreaVerdict::InGap => "left out, in a gap wider than 0.3 mag",
+                    AreaVerdict::Fallback => "kept, all areas are in a gap wider than 0.3 mag",
+                    AreaVerdict::BelowHorizon => "left out, below the horizon",
+                }
+            )?;
+        }
+        let kept = self.areas.iter().filter(|a| a.is_kept()).count();
+        write!(
+            f,
+            "{} of {} area{}: Lm {:.2}",
+            if self.is_fallback() {
+                "plain average"
+            } else {
+                "average"
+            },
+            kept,
+            if kept == 1 { "" } else { "s" },
+            self.average
+        )
+    }
+}
+
+// The correction gives what has to be added to the limiting magnitude of an area, which is
+// zero unless the extinction is corrected for, or None for an area below the horizon. The
+// gap rule and the average use the corrected values of the other areas; without any, there
+// is no limiting magnitude.
+pub fn explain_limiting_magnitude(
+    counts: &[(usize, Area)],
+    correction: impl Fn(Area) -> Option<f64>,
+) -> Option<LmExplanation> {
+    let table_lms: Vec<f64> = counts
         .iter()
         .flat_map(|count| get_limiting_magnitude(count.0, count.1))
         .collect();
-    lms.sort_by(|a, b| a.partial_cmp(b).unwrap());
-
-    if lms.is_empty() || lms.len() != counts.len() {
+    if table_lms.is_empty() || table_lms.len() != counts.len() {
+        return None;
+    }
+    let corrections: Vec<Option<f64>> = counts.iter().map(|count| correction(count.1)).collect();
+    let lms: Vec<f64> = table_lms
+        .iter()
+        .zip(&corrections)
+        .filter_map(|(lm, correction)| correction.map(|c| lm + c))
+        .collect();
+    if lms.is_empty() {
         return None;
-    } else if lms.len() == 1 {
-        return Some(lms[0]);
     }
+    let mut sorted = lms.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
     // IMO handbook page 55:
     // "Whenever your limiting magnitude lies in a ‘gap’ wider than 0.3 mag, you should ignore this field"
-    let mut selected: Vec<f64> = vec![];
-    for i in 0..lms.len() {
+    let in_gap = |lm: f64| {
+        let i = sorted.iter().position(|&l| l == lm).unwrap();
         let left_gap = match i {
             0 => true,
-            _ => lms[i] - lms[i - 1] > 0.3,
+            _ => sorted[i] - sorted[i - 1] > 0.3,
         };
-        let right_gap = match lms.get(i + 1) {
-            Some(next_lm) => next_lm - lms[i] > 0.3,
+        let right_gap = match sorted.get(i + 1) {
+            Some(next_lm) => next_lm - sorted[i] > 0.3,
             None => true,
         };
-        if !left_gap || !right_gap {
-            selected.push(lms[i]);
-        }
-    }
-
-    if selected.is_empty() {
+        left_gap && right_gap
+    };
+    let mut verdicts: Vec<AreaVerdict> = lms
+        .iter()
+        .map(|&lm| match lms.len() > 1 && in_gap(lm) {
+            true => AreaVerdict::InGap,
+            false => AreaVerdict::Kept,
+        })
+        .collect();
+    if verdicts.iter().all(|v| *v == AreaVerdict::InGap) {
         // Every magnitude lies in such a gap so let's take the overall average.
-        Some(((lms.iter().sum::<f64>() / (lms.len() as f64)) * 100_f64).round() / 100_f64)
-    } else {
-        Some(((selected.iter().sum::<f64>() / (selected.len() as f64)) * 100_f64).round() / 100_f64)
+        verdicts = vec![AreaVerdict::Fallback; lms.len()];
     }
+
+    let kept: Vec<f64> = lms
+        .iter()
+        .zip(&verdicts)
+        .filter(|(_, v)| **v != AreaVerdict::InGap)
+        .map(|(lm, _)| *lm)
+        .collect();
+    let average = ((kept.iter().sum::<f64>() / (kept.len() as f64)) * 100_f64).round() / 100_f64;
+
+    // The verdicts are for the areas above the horizon, in order.
+    let mut verdicts = verdicts.into_iter();
+    Some(LmExplanation {
+        areas: counts
+            .iter()
+            .zip(table_lms.into_iter().zip(corrections))
+            .map(
+                |(&(stars, area), (limiting_magnitude, correction))| AreaLm {
+                    stars,
+                    area,
+                    limiting_magnitude,
+                    correction: correction.unwrap_or(0_f64),
+                    verdict: match correction {
+                        Some(_) => verdicts.next().unwrap(),
+                        None => AreaVerdict::BelowHorizon,
+                    },
+                },
+            )
+            .collect(),
+        average,
+    })
 }
 
 #[cfg(test)]
@@ -245,13 +507,19 @@ mod tests {
 
     #[test]
     pub fn test_limiting_magnitude_average_1() {
-        assert_eq!(get_limiting_magnitude_avg(&[(11, Area(14))]), Some(5.64));
+        assert_eq!(
+            explain_limiting_magnitude(&[(11, Area(14))], |_| Some(0_f64)).map(|e| e.average),
+            Some(5.64)
+        );
     }
 
     #[test]
     pub fn test_limiting_magnitude_average_2() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[(11, Area(14)), (10, Area(7)), (8, Area(2))]),
+            explain_limiting_magnitude(&[(11, Area(14)), (10, Area(7)), (8, Area(2))], |_| Some(
+                0_f64
+            ))
+            .map(|e| e.average),
             Some(5.53)
         );
     }
@@ -259,7 +527,10 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_3() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[(11, Area(14)), (10, Area(7)), (15, Area(2))]),
+            explain_limiting_magnitude(&[(11, Area(14)), (10, Area(7)), (15, Area(2))], |_| Some(
+                0_f64
+            ))
+            .map(|e| e.average),
             Some(5.50)
         );
     }
@@ -267,7 +538,10 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_4() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[(1, Area(14)), (10, Area(7)), (15, Area(2))]),
+            explain_limiting_magnitude(&[(1, Area(14)), (10, Area(7)), (15, Area(2))], |_| Some(
+                0_f64
+            ))
+            .map(|e| e.average),
             Some(4.67)
         );
     }
@@ -275,12 +549,11 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_5() {
         assert_eq!(
-            get_limiting_magnitude_avg(&vec![
-                (1, Area(14)),
-                (10, Area(7)),
-                (15, Area(2)),
-                (3, Area(35))
-            ]),
+            explain_limiting_magnitude(
+                &vec![(1, Area(14)), (10, Area(7)), (15, Area(2)), (3, Area(35))],
+                |_| Some(0_f64)
+            )
+            .map(|e| e.average),
             None
         );
     }
@@ -288,12 +561,11 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_6() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[
-                (11, Area(14)),
-                (10, Area(7)),
-                (15, Area(2)),
-                (100, Area(3))
-            ]),
+            explain_limiting_magnitude(
+                &[(11, Area(14)), (10, Area(7)), (15, Area(2)), (100, Area(3))],
+                |_| Some(0_f64)
+            )
+            .map(|e| e.average),
             None
         );
     }
@@ -301,7 +573,10 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_7() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[(110, Area(14)), (10, Area(70)), (153, Area(2))]),
+            explain_limiting_magnitude(&[(110, Area(14)), (10, Area(70)), (153, Area(2))], |_| {
+                Some(0_f64)
+            })
+            .map(|e| e.average),
             None
         );
     }
@@ -309,8 +584,125 @@ mod tests {
     #[test]
     pub fn test_limiting_magnitude_average_8() {
         assert_eq!(
-            get_limiting_magnitude_avg(&[(12, Area(14)), (12, Area(7)), (10, Area(6))]),
+            explain_limiting_magnitude(&[(12, Area(14)), (12, Area(7)), (10, Area(6))], |_| Some(
+                0_f64
+            ))
+            .map(|e| e.average),
             Some(5.91),
         );
     }
+
+    #[test]
+    pub fn test_explain_limiting_magnitude() {
+        let explanation =
+            explain_limiting_magnitude(&[(11, Area(14)), (10, Area(7)), (1, Area(14))], |_| {
+                Some(0_f64)
+            })
+            .unwrap();
+        let verdicts: Vec<AreaVerdict> = explanation.areas.iter().map(|a| a.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![AreaVerdict::Kept, AreaVerdict::Kept, AreaVerdict::InGap]
+        );
+        assert_eq!(explanation.areas[2].limiting_magnitude, 2.23);
+        assert_eq!(explanation.average, 5.5);
+        assert!(!explanation.is_fallback());
+        assert_eq!(
+            explanation.to_string(),
+            "area 14,  11 stars: Lm 5.64, kept
+area  7,  10 stars: Lm 5.36, kept
+area 14,   1 stars: Lm 2.23, left out, in a gap wider than 0.3 mag
+average of 2 areas: Lm 5.50"
+        );
+
+        let explanation =
+            explain_limiting_magnitude(&[(1, Area(14)), (11, Area(14))], |_| Some(0_f64)).unwrap();
+        assert!(explanation.is_fallback());
+        assert_eq!(explanation.average, 3.93);
+        assert!(explanation
+            .to_string()
+            .ends_with("plain average of 2 areas: Lm 3.93"));
+        assert_eq!(
+            explain_limiting_magnitude(&[(3, Area(35))], |_| Some(0_f64)),
+            None
+        );
+
+        // Area 7 gets 0.3 mag more, which closes the gap to area 14.
+        let explanation = explain_limiting_magnitude(&[(11, Area(14)), (8, Area(7))], |a| {
+            if a == Area(7) {
+                Some(0.3)
+            } else {
+                Some(0_f64)
+            }
+        })
+        .unwrap();
+        assert_eq!(explanation.areas[1].limiting_magnitude, 5.26);
+        assert!((explanation.areas[1].corrected() - 5.56).abs() < 1e-9);
+        assert!(!explanation.is_fallback());
+        assert_eq!(explanation.average, 5.6);
+        assert!(explanation.to_string().starts_with(
+            "area 14,  11 stars: Lm 5.64, kept\narea  7,   8 stars: Lm 5.26, corrected 5.56, kept"
+        ));
+
+        // Area 7 is below the horizon, so it is left out of the gap rule and the average.
+        let explanation =
+            explain_limiting_magnitude(&[(11, Area(14)), (1, Area(7)), (10, Area(7))], |a| {
+                if a == Area(7) {
+                    None
+                } else {
+                    Some(0_f64)
+                }
+            })
+            .unwrap();
+        assert_eq!(explanation.areas[1].verdict, AreaVerdict::BelowHorizon);
+        assert_eq!(explanation.average, 5.64);
+        assert!(explanation
+            .to_string()
+            .contains("area  7,   1 stars: Lm 2.47, left out, below the horizon"));
+        assert_eq!(
+            explain_limiting_magnitude(&[(11, Area(14))], |_| None),
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_area_altitudes() {
+        let centres = AreaCentres::parse("AREA;RA;DEC\n14;279.2;38.8\n\n7;0;-80").unwrap();
+        let gent = Location {
+            latitude: 51.05,
+            longitude: 3.72,
+            elevation: 10.0,
+        };
+        // 12 Aug 2019 22:00 UT, when Vega is high in the west
+        let jd = astro::julian_day(2019, 8, 12, 22, 0);
+        let altitudes = centres.altitudes(gent, jd, 20.0);
+        assert_eq!(altitudes.len(), 2);
+        assert_eq!(altitudes[0].area, Area(14));
+        assert!(altitudes[0].altitude > 60.0);
+        assert_eq!(altitudes[0].advice, AreaAdvice::Recommended);
+        assert_eq!(altitudes[1].advice, AreaAdvice::Unusable);
+        assert_eq!(centres.altitude(Area(3), gent, jd), None);
+
+        assert_eq!(
+            AreaCentres::parse("AREA;RA;DEC\n31;0;0").unwrap_err().line,
+            2
+        );
+        assert!(AreaCentres::parse("AREA;RA;DEC\n1;0").is_err());
+        assert!(AreaCentres::parse("AREA;RA;DEC\n1;360;0").is_err());
+        assert!(AreaCentres::parse("AREA;RA;DEC\n1;-10;0").is_err());
+        assert_eq!(
+            AreaCentres::parse("AREA;RA;DEC\n1;0;0\n2;0;0\n1;10;0")
+                .unwrap_err()
+                .line,
+            4
+        );
+    }
+
+    #[test]
+    pub fn test_bundled_area_centres() {
+        // Panics when data/area_centres.csv is invalid. The table is left empty until the
+        // centres of the IMO handbook are filled in, and then it has to list every area.
+        let areas: Vec<usize> = AreaCentres::bundled().0.keys().copied().collect();
+        assert!(areas.is_empty() || areas == (1..=AREAS.len()).collect::<Vec<usize>>());
+    }
 }
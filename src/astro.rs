@@ -0,0 +1,143 @@
+// Formulas from Jean Meeus, "Astronomical Algorithms" (2nd edition).
+
+pub fn julian_day(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> f64 {
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 12)
+    } else {
+        (year, month)
+    };
+    let a = (f64::from(y) / 100_f64).floor();
+    let b = 2_f64 - a + (a / 4_f64).floor();
+    (365.25 * (f64::from(y) + 4716_f64)).floor()
+        + (30.6001 * (f64::from(m) + 1_f64)).floor()
+        + f64::from(day)
+        + b
+        - 1524.5
+        + (f64::from(hour) * 60_f64 + f64::from(minute)) / 1440_f64
+}
+
+// Greenwich mean sidereal time in degrees.
+pub fn sidereal_time(jd: f64) -> f64 {
+    let t = (jd - 2_451_545_f64) / 36525_f64;
+    let theta = 280.460_618_37 + 360.985_647_366_29 * (jd - 2_451_545_f64) + 0.000_387_933 * t * t
+        - t * t * t / 38_710_000_f64;
+    theta.rem_euclid(360_f64)
+}
+
+// Altitude in degrees of the point (ra, dec) for an observer at (latitude, longitude),
+// with longitudes counted positive to the east.
+pub fn altitude(ra: f64, dec: f64, latitude: f64, longitude: f64, jd: f64) -> f64 {
+    let hour_angle = (sidereal_time(jd) + longitude - ra).to_radians();
+    let (lat, dec) = (latitude.to_radians(), dec.to_radians());
+    (lat.sin() * dec.sin() + lat.cos() * dec.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+// Angle in degrees between the points (ra1, dec1) and (ra2, dec2) on the sky.
+pub fn angular_distance(ra1: f64, dec1: f64, ra2: f64, dec2: f64) -> f64 {
+    // Haversine formula, which stays accurate for small distances.
+    let (dec1, dec2) = (dec1.to_radians(), dec2.to_radians());
+    let half_ra = (ra1 - ra2).to_radians() / 2_f64;
+    let half_dec = (dec1 - dec2) / 2_f64;
+    let h = half_dec.sin().powi(2) + dec1.cos() * dec2.cos() * half_ra.sin().powi(2);
+    (2_f64 * h.sqrt().min(1_f64).asin()).to_degrees()
+}
+
+// Airmass at the given altitude in degrees, with the formula of Kasten and Young (1989),
+// which stays finite down to the horizon.
+pub fn airmass(altitude: f64) -> f64 {
+    1_f64 / (altitude.to_radians().sin() + 0.505_72 * (altitude + 6.079_95).powf(-1.636_4))
+}
+
+// How much fainter a star at the given altitude looks than at the zenith, in magnitudes,
+// for the extinction coefficient k (magnitudes per airmass).
+pub fn extinction(altitude: f64, k: f64) -> f64 {
+    k * (airmass(altitude) - 1_f64)
+}
+
+// Solar longitude in degrees, referred to the J2000 equinox as usual for meteor work.
+pub fn solar_longitude(jd: f64) -> f64 {
+    let t = (jd - 2_451_545_f64) / 36525_f64;
+    let mean_longitude = 280.466_46 + 36_000.769_83 * t + 0.000_303_2 * t * t;
+    let mean_anomaly = (357.529_11 + 35_999.050_29 * t - 0.000_153_7 * t * t).to_radians();
+    let center = (1.914_602 - 0.004_817 * t - 0.000_014 * t * t) * mean_anomaly.sin()
+        + (0.019_993 - 0.000_101 * t) * (2_f64 * mean_anomaly).sin()
+        + 0.000_289 * (3_f64 * mean_anomaly).sin();
+    // Precession from the equinox of date back to J2000.
+    (mean_longitude + center - 1.397 * t).rem_euclid(360_f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(a: f64) -> f64 {
+        (a * 100_f64).round() / 100_f64
+    }
+
+    #[test]
+    fn test_julian_day_1() {
+        assert_eq!(julian_day(2000, 1, 1, 12, 0), 2_451_545.0);
+    }
+
+    #[test]
+    fn test_julian_day_2() {
+        assert_eq!(julian_day(1987, 4, 10, 0, 0), 2_446_895.5);
+    }
+
+    #[test]
+    fn test_sidereal_time() {
+        // Meeus, example 12.a: 1987 April 10, 0h UT.
+        assert_eq!(round(sidereal_time(2_446_895.5)), 197.69);
+    }
+
+    #[test]
+    fn test_altitude_1() {
+        // A star on the meridian passing through the zenith.
+        let jd = julian_day(2019, 8, 12, 23, 0);
+        let lst = sidereal_time(jd) + 4.0;
+        assert_eq!(round(altitude(lst, 51.0, 51.0, 4.0, jd)), 90.0);
+    }
+
+    #[test]
+    fn test_altitude_2() {
+        // The celestial pole is at an altitude equal to the latitude.
+        let jd = julian_day(2019, 8, 12, 23, 0);
+        assert_eq!(round(altitude(123.0, 90.0, 51.0, 4.0, jd)), 51.0);
+        assert_eq!(round(altitude(123.0, -90.0, 51.0, 4.0, jd)), -51.0);
+    }
+
+    #[test]
+    fn test_solar_longitude_1() {
+        // Perseid maximum of 2019 Aug 13, 07h UT: 140.0 degrees.
+        assert_eq!(
+            (solar_longitude(julian_day(2019, 8, 13, 7, 0)) * 10.0).round() / 10.0,
+            140.0
+        );
+    }
+
+    #[test]
+    fn test_solar_longitude_2() {
+        // Geminid maximum of 2019 Dec 14, 18h UT: 262.2 degrees.
+        assert_eq!(
+            (solar_longitude(julian_day(2019, 12, 14, 18, 0)) * 10.0).round() / 10.0,
+            262.2
+        );
+    }
+
+    #[test]
+    fn test_extinction() {
+        assert_eq!(round(airmass(90.0)), 1.0);
+        assert_eq!(round(airmass(30.0)), 1.99);
+        assert_eq!(round(extinction(90.0, 0.3)), 0.0);
+        assert_eq!(round(extinction(45.0, 0.3)), 0.12);
+    }
+
+    #[test]
+    fn test_angular_distance() {
+        assert_eq!(round(angular_distance(10.0, 20.0, 10.0, 20.0)), 0.0);
+        assert_eq!(round(angular_distance(0.0, 0.0, 90.0, 0.0)), 90.0);
+        assert_eq!(round(angular_distance(350.0, 89.0, 170.0, 89.0)), 2.0);
+    }
+}
@@ -1,59 +1,219 @@
-use crate::areas::get_limiting_magnitude_avg;
+use crate::areas::{explain_limiting_magnitude, Area, AreaCentres, LmExplanation};
+use crate::astro;
+use crate::catalog::Catalog;
+use crate::diagnostic::{Diagnostic, Label};
 use crate::factors;
 use crate::field::Field;
+use crate::location::Location;
 use crate::meteor::{Meteor, Shower};
 use crate::session::*;
+use crate::sqm::SqmConversion;
 use crate::timestamp;
-use crate::timestamp::Timestamp;
+use crate::timestamp::{Date, DateTime, Timestamp};
 
 pub struct SessionBuilder {
     periods: Vec<Period>,
     current: IncompletePeriod,
+    catalog: Catalog,
+    strict: bool,
+    sqm_conversion: SqmConversion,
+    // The area centres and the minimum altitude of counted areas, to warn about low areas.
+    area_check: Option<(AreaCentres, f64)>,
+    // The extinction coefficient, to correct the limiting magnitude of areas for their altitude.
+    extinction: Option<f64>,
+    warnings: Vec<BuilderWarning>,
+    // How the limiting magnitude of every `areas` was found, with its line and time.
+    lm_explanations: Vec<(usize, Timestamp, LmExplanation)>,
+    // The numbers of the periods that were discarded.
+    discarded_periods: Vec<usize>,
+    header: SessionHeader,
+    site_line: Option<usize>,
 }
 
 impl SessionBuilder {
-    pub fn new() -> Self {
+    pub fn new(catalog: Catalog) -> Self {
         Self {
             periods: vec![],
             current: IncompletePeriod::new(),
+            catalog,
+            strict: false,
+            sqm_conversion: SqmConversion::default(),
+            area_check: None,
+            extinction: None,
+            warnings: vec![],
+            lm_explanations: vec![],
+            discarded_periods: vec![],
+            header: SessionHeader::default(),
+            site_line: None,
         }
     }
 
-    pub fn into_session(mut self) -> Result<Session, BuilderError> {
-        self.periods.push(self.current.into_period()?);
-        Ok(Session {
+    // In strict mode, situations that normally give a warning are errors.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn set_sqm_conversion(&mut self, sqm_conversion: SqmConversion) {
+        self.sqm_conversion = sqm_conversion;
+    }
+
+    pub fn set_area_check(&mut self, area_check: Option<(AreaCentres, f64)>) {
+        self.area_check = area_check;
+    }
+
+    pub fn set_extinction(&mut self, extinction: Option<f64>) {
+        self.extinction = extinction;
+    }
+
+    pub fn warnings(&self) -> &[BuilderWarning] {
+        &self.warnings
+    }
+
+    pub fn lm_explanations(&self) -> &[(usize, Timestamp, LmExplanation)] {
+        &self.lm_explanations
+    }
+
+    pub fn header(&self) -> &SessionHeader {
+        &self.header
+    }
+
+    // Drops the events of the period that is being built.
+    pub fn discard_period(&mut self) {
+        let current = std::mem::replace(&mut self.current, IncompletePeriod::new());
+        self.forget_period(&current.lines.limiting_magnitudes);
+    }
+
+    // Counts a period that did not make it into the session, and drops the Lm explanations
+    // of its lines.
+    fn forget_period(&mut self, lm_lines: &[usize]) {
+        self.lm_explanations.retain(|e| !lm_lines.contains(&e.0));
+        self.discarded_periods.push(self.period_number());
+    }
+
+    // The number of the period that is being built, counting discarded periods as well.
+    pub fn period_number(&self) -> usize {
+        self.periods.len() + self.discarded_periods.len() + 1
+    }
+
+    // The numbers of the finished periods, as in the diagnostics.
+    pub fn finished_period_numbers(&self) -> Vec<usize> {
+        (1..self.period_number())
+            .filter(|n| !self.discarded_periods.contains(n))
+            .collect()
+    }
+
+    // Running totals of the period that is being built, up to `now`.
+    pub fn tally(&self, now: Option<Timestamp>) -> Tally {
+        let current = &self.current;
+        let teff_minutes = match (
+            current.start_time,
+            current.end_time.or(current.current_break).or(now),
+        ) {
+            (Some(start), Some(end)) => {
+                timestamp::effective_time_minutes(start, end, &current.breaks).unwrap_or(0)
+            }
+            _ => 0,
+        };
+        let counts = current
+            .showers
+            .as_ref()
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|shower| {
+                let count = current
+                    .meteors
+                    .iter()
+                    .filter(|m| m.shower == *shower)
+                    .count();
+                (*shower, count as u32)
+            })
+            .collect();
+
+        Tally {
+            period: self.period_number(),
+            teff: f64::from(teff_minutes) / 60_f64,
+            limiting_magnitude: current.limiting_magnitudes.last().map(|lm| lm.0.value),
+            cloud_factor: current
+                .clouds
+                .last()
+                .map(|c| factors::cloud_factor(&[(c.0, 1)])),
+            counts,
+        }
+    }
+
+    pub fn into_session(mut self) -> Result<Session, LocatedError> {
+        self.finish_checks()?;
+        let number = self.period_number();
+        let current = std::mem::replace(&mut self.current, IncompletePeriod::new());
+        let period = current.into_period(self.periods.last(), number, None)?;
+        self.periods.push(period);
+        Ok(self.into_finished_session())
+    }
+
+    // The session of the periods that are finished, leaving out the one that is being built.
+    // For when that period was discarded.
+    pub fn into_finished_session(mut self) -> Session {
+        // Periods without a location of their own were observed at the site.
+        let site_location = self.header.site.as_ref().map(|s| s.location);
+        for period in &mut self.periods {
+            period.location = period.location.or(site_location);
+        }
+        Session {
+            header: self.header,
             periods: self.periods,
-        })
+        }
     }
 
-    pub fn register_event(&mut self, time_and_event: TimestampedEvent) -> Result<(), BuilderError> {
-        let TimestampedEvent(timestamp, event) = time_and_event;
+    pub fn register_event(&mut self, time_and_event: TimestampedEvent) -> Result<(), LocatedError> {
+        let TimestampedEvent(timestamp, event, line) = time_and_event;
 
         if self.current.current_break.is_some() {
             match event {
                 Event::BreakEnd => {}
-                _ => return Err(BuilderError::InBreak),
+                _ if event.is_header() => {}
+                _ => return Err(self.error(BuilderError::InBreak, line)),
             }
         }
+        if event != Event::NewPeriod && !event.is_header() && self.current.lines.first.is_none() {
+            self.current.lines.first = Some(line);
+        }
 
         match event {
             Event::NewPeriod => {
+                if let Err(e) = self.finish_checks() {
+                    let c = std::mem::replace(&mut self.current, IncompletePeriod::new());
+                    self.forget_period(&c.lines.limiting_magnitudes);
+                    return Err(e);
+                }
                 let mut c = IncompletePeriod::new();
                 std::mem::swap(&mut c, &mut self.current);
-                self.periods.push(c.into_period()?);
+                let lm_lines = c.lines.limiting_magnitudes.clone();
+                let number = self.period_number();
+                match c.into_period(self.periods.last(), number, Some(line)) {
+                    Ok(period) => self.periods.push(period),
+                    Err(e) => {
+                        self.forget_period(&lm_lines);
+                        return Err(e);
+                    }
+                }
             }
             Event::PeriodStart => {
                 self.current.start_time = Some(timestamp);
+                self.current.lines.start = Some(line);
+                self.check_activity(line)?;
             }
             Event::PeriodEnd => {
                 self.current.end_time = Some(timestamp);
+                self.current.lines.end = Some(line);
             }
             Event::PeriodDate(date) => {
                 if self.current.date.is_some() {
-                    return Err(BuilderError::AlreadyDate);
+                    return Err(self.error(BuilderError::AlreadyDate, line));
                 }
 
                 self.current.date = Some(date);
+                self.current.lines.date = Some(line);
+                self.check_activity(line)?;
             }
             Event::Meteor(meteor) => {
                 if !self
@@ -63,61 +223,298 @@ impl SessionBuilder {
                     .unwrap_or(&vec![])
                     .contains(&meteor.shower)
                 {
-                    return Err(BuilderError::NotObservingShower);
+                    return Err(self.error(BuilderError::NotObservingShower, line));
                 }
-                self.current.meteors.push(meteor);
+                self.current.meteors.push(Meteor {
+                    time: Some(timestamp),
+                    line: Some(line),
+                    ..meteor
+                });
             }
             Event::Field(field) => {
                 if self.current.field.is_some() {
-                    return Err(BuilderError::AlreadyField);
+                    return Err(self.error(BuilderError::AlreadyField, line));
                 } else {
                     self.current.field = Some(field);
+                    self.current.lines.field = Some(line);
+                }
+            }
+            Event::Location(location) => {
+                if self.current.location.is_some() {
+                    return Err(self.error(BuilderError::AlreadyLocation, line));
+                } else {
+                    self.current.location = Some(location);
+                    self.current.lines.location = Some(line);
                 }
             }
             Event::AreasCounted(counts) => {
-                let maybe_lm_avg = get_limiting_magnitude_avg(&counts);
-                if let Some(lm_avg) = maybe_lm_avg {
-                    self.current.limiting_magnitudes.push((lm_avg, timestamp));
+                // Corrected for extinction when the period ends, once its date and location
+                // are known.
+                if let Some(explanation) = explain_limiting_magnitude(&counts, |_| Some(0_f64)) {
+                    self.current.area_counts.push(AreaCounts {
+                        counts,
+                        time: timestamp,
+                        line,
+                        lm: self.current.limiting_magnitudes.len(),
+                    });
+                    self.current
+                        .push_lm(explanation.average, LmMethod::Areas, timestamp);
+                    self.current.lines.limiting_magnitudes.push(line);
                 } else {
-                    return Err(BuilderError::InvalidLm);
+                    return Err(self.error(BuilderError::InvalidLm, line));
                 }
             }
+            Event::LimitingMagnitude(lm) => {
+                self.current.push_lm(lm, LmMethod::Estimate, timestamp);
+                self.current.lines.limiting_magnitudes.push(line);
+            }
+            Event::Sqm(reading) => {
+                let lm = self.sqm_conversion.limiting_magnitude(reading);
+                self.current.push_lm(lm, LmMethod::Sqm(reading), timestamp);
+                self.current.lines.limiting_magnitudes.push(line);
+            }
             Event::Clouds(clouds) => {
                 self.current.clouds.push((clouds, timestamp));
+                self.current.lines.clouds.push(line);
             }
             Event::BreakStart => {
                 self.current.current_break = Some(timestamp);
+                self.current.lines.current_break = Some(line);
             }
-            Event::BreakEnd => match self.current.current_break {
-                Some(br) => {
+            Event::BreakEnd => match (self.current.current_break, self.current.lines.current_break)
+            {
+                (Some(br), Some(br_line)) => {
                     self.current.breaks.push((br, timestamp));
+                    self.current.lines.breaks.push((br_line, line));
                     self.current.current_break = None;
+                    self.current.lines.current_break = None;
                 }
-                None => return Err(BuilderError::NoBreakToEnd),
+                _ => return Err(self.error(BuilderError::NoBreakToEnd, line)),
             },
             Event::Showers(showers) => {
                 if self.current.showers.is_some() {
-                    return Err(BuilderError::AlreadyShowers);
+                    return Err(self.error(BuilderError::AlreadyShowers, line));
                 }
 
                 self.current.showers = Some(showers);
+                self.current.lines.showers = Some(line);
+                self.check_activity(line)?;
+            }
+            // The interpreter gives every observer a builder of their own, so the observer
+            // is only given once here.
+            Event::Observer(observer) => self.header.observer = Some(observer),
+            Event::Site(site) => {
+                if let Some(first) = self.site_line {
+                    return Err(self.header_error(BuilderError::AlreadySite, first, line));
+                }
+                self.header.site = Some(site);
+                self.site_line = Some(line);
             }
         };
         Ok(())
     }
+
+    fn error(&self, error: BuilderError, line: usize) -> LocatedError {
+        self.current.locate(error, self.period_number(), Some(line))
+    }
+
+    // The observer and site are given once per file, so the first line is not part of
+    // the period.
+    fn header_error(&self, error: BuilderError, first: usize, line: usize) -> LocatedError {
+        let mut e = self.error(error, line);
+        e.labels.insert(0, Label::new(first, "first given here"));
+        e
+    }
+
+    fn check_activity(&mut self, line: usize) -> Result<(), LocatedError> {
+        if self.current.activity_checked {
+            return Ok(());
+        }
+        let date = self.current.date(self.periods.last());
+        let (date, showers) = match (date, &self.current.showers) {
+            (Some(date), Some(showers)) => (date, showers),
+            _ => return Ok(()),
+        };
+        self.current.activity_checked = true;
+
+        for shower in showers {
+            let active = match self.catalog.get(*shower) {
+                Some(info) => info.is_active_on(date.month, date.day),
+                None => true,
+            };
+            if !active {
+                if self.strict {
+                    return Err(self.error(BuilderError::ShowerNotActive, line));
+                }
+                self.warnings
+                    .push(BuilderWarning::ShowerNotActive(*shower, date));
+            }
+        }
+        Ok(())
+    }
+
+    // Runs the checks and corrections that need the whole period, because the date and
+    // location may come after the lines they are about. Done when the period ends, and at
+    // the end of the input. In strict mode, areas that cannot be corrected are refused
+    // before anything changes, so running the checks again gives the same error.
+    pub fn finish_checks(&mut self) -> Result<(), LocatedError> {
+        let all_corrections: Vec<_> = self
+            .current
+            .area_counts
+            .iter()
+            .map(|areas| self.extinction_corrections(areas))
+            .collect();
+        if self.strict {
+            let refused = self
+                .current
+                .area_counts
+                .iter()
+                .zip(&all_corrections)
+                .find_map(|(areas, c)| c.as_ref().err().map(|reason| (areas.line, *reason)));
+            if let Some((line, reason)) = refused {
+                let mut e = self.error(BuilderError::NotCorrected, line);
+                e.notes.insert(0, reason.to_string());
+                return Err(e);
+            }
+        }
+
+        let area_counts = std::mem::take(&mut self.current.area_counts);
+        for (areas, corrections) in area_counts.into_iter().zip(all_corrections) {
+            self.check_area_altitudes(&areas.counts, areas.time, areas.line);
+            // Without a correction, the Lm stays as it was found from the table.
+            let corrections = corrections.unwrap_or_else(|reason| {
+                self.warnings
+                    .push(BuilderWarning::NotCorrected(reason, areas.line));
+                areas.counts.iter().map(|c| (c.1, Some(0_f64))).collect()
+            });
+            let explanation = explain_limiting_magnitude(&areas.counts, |area| {
+                corrections.iter().find(|c| c.0 == area).and_then(|c| c.1)
+            });
+            if let Some(explanation) = explanation {
+                self.current.limiting_magnitudes[areas.lm].0.value = explanation.average;
+                self.lm_explanations
+                    .push((areas.line, areas.time, explanation));
+            }
+        }
+        Ok(())
+    }
+
+    // The altitude of the centre of the area at the given time, when the centres, the
+    // location and the date are known. Like the date, the location can be left out after
+    // the first period, and falls back to the site.
+    fn area_altitude(&self, area: Area, time: Timestamp) -> Result<f64, Uncorrected> {
+        let centres = match &self.area_check {
+            Some((centres, _)) => centres,
+            None => return Err(Uncorrected::NoCentre(area)),
+        };
+        let site_location = self.header.site.as_ref().map(|s| s.location);
+        let location = self
+            .current
+            .location
+            .or_else(|| self.periods.last().and_then(|p| p.location))
+            .or(site_location)
+            .ok_or(Uncorrected::NoLocation)?;
+        let start = DateTime {
+            date: self
+                .current
+                .date(self.periods.last())
+                .ok_or(Uncorrected::NoDate)?,
+            time: self.current.start_time.unwrap_or(time),
+        };
+        centres
+            .altitude(area, location, start.resolve(time).julian_day())
+            .ok_or(Uncorrected::NoCentre(area))
+    }
+
+    // What to add to the limiting magnitude of each area for the extinction at its
+    // altitude, or None for an area below the horizon, which is left out. Without
+    // extinction, nothing is added.
+    fn extinction_corrections(
+        &self,
+        areas: &AreaCounts,
+    ) -> Result<Vec<(Area, Option<f64>)>, Uncorrected> {
+        let k = match self.extinction {
+            Some(k) => k,
+            None => return Ok(areas.counts.iter().map(|c| (c.1, Some(0_f64))).collect()),
+        };
+        let mut corrections = vec![];
+        for (_, area) in &areas.counts {
+            let altitude = self.area_altitude(*area, areas.time)?;
+            let correction = if altitude > 0_f64 {
+                Some(astro::extinction(altitude, k))
+            } else {
+                None
+            };
+            corrections.push((*area, correction));
+        }
+        if corrections.iter().all(|c| c.1.is_none()) {
+            return Err(Uncorrected::BelowHorizon);
+        }
+        Ok(corrections)
+    }
+
+    // Warns about counted areas whose centre is below the minimum altitude.
+    fn check_area_altitudes(&mut self, counts: &[(usize, Area)], time: Timestamp, line: usize) {
+        let min_altitude = match &self.area_check {
+            Some((_, min_altitude)) => *min_altitude,
+            None => return,
+        };
+        for (_, area) in counts {
+            if let Ok(altitude) = self.area_altitude(*area, time) {
+                if altitude < min_altitude {
+                    self.warnings.push(BuilderWarning::AreaTooLow(
+                        *area,
+                        altitude,
+                        min_altitude,
+                        line,
+                    ));
+                }
+            }
+        }
+    }
 }
 
 struct IncompletePeriod {
     start_time: Option<Timestamp>,
     end_time: Option<Timestamp>,
-    date: Option<String>,
+    date: Option<Date>,
     field: Option<Field>,
+    location: Option<Location>,
     showers: Option<Vec<Shower>>,
     meteors: Vec<Meteor>,
-    limiting_magnitudes: Vec<(f64, Timestamp)>,
+    limiting_magnitudes: Vec<(LimitingMagnitude, Timestamp)>,
+    area_counts: Vec<AreaCounts>,
     clouds: Vec<(u8, Timestamp)>,
     breaks: Vec<(Timestamp, Timestamp)>,
     current_break: Option<Timestamp>,
+    activity_checked: bool,
+    lines: PeriodLines,
+}
+
+// An `areas` line, kept to check the altitudes of the areas and correct its Lm for
+// extinction when the period ends.
+struct AreaCounts {
+    counts: Vec<(usize, Area)>,
+    time: Timestamp,
+    line: usize,
+    // The index of its Lm in the checkpoints.
+    lm: usize,
+}
+
+// The input lines of the events of a period, to point at them in errors.
+#[derive(Default)]
+struct PeriodLines {
+    first: Option<usize>,
+    start: Option<usize>,
+    end: Option<usize>,
+    date: Option<usize>,
+    field: Option<usize>,
+    location: Option<usize>,
+    showers: Option<usize>,
+    limiting_magnitudes: Vec<usize>,
+    clouds: Vec<usize>,
+    breaks: Vec<(usize, usize)>,
+    current_break: Option<usize>,
 }
 
 impl IncompletePeriod {
@@ -127,71 +524,111 @@ impl IncompletePeriod {
             end_time: None,
             date: None,
             field: None,
+            location: None,
             showers: None,
             meteors: vec![],
             limiting_magnitudes: vec![],
+            area_counts: vec![],
             clouds: vec![],
             breaks: vec![],
             current_break: None,
+            activity_checked: false,
+            lines: PeriodLines::default(),
         }
     }
 
-    fn into_period(self) -> Result<Period, BuilderError> {
+    fn push_lm(&mut self, value: f64, method: LmMethod, time: Timestamp) {
+        self.limiting_magnitudes
+            .push((LimitingMagnitude { value, method }, time));
+    }
+
+    // The date can be omitted after the first period: then it follows from the end of the
+    // previous period, advancing when the clock went past midnight.
+    fn date(&self, previous: Option<&Period>) -> Option<Date> {
+        match (self.date, previous, self.start_time) {
+            (Some(date), _, _) => Some(date),
+            (None, Some(previous), Some(start_time)) => {
+                Some(previous.end().resolve(start_time).date)
+            }
+            _ => None,
+        }
+    }
+
+    // `number` is the number of the period in the session, and `line` the line that ends it
+    // (None at the end of the input).
+    fn into_period(
+        self,
+        previous: Option<&Period>,
+        number: usize,
+        line: Option<usize>,
+    ) -> Result<Period, LocatedError> {
+        let error = |e| Err(self.locate(e, number, line));
+
         if self.clouds.is_empty() {
-            return Err(BuilderError::NoF);
+            return error(BuilderError::NoF);
         }
         if self.limiting_magnitudes.is_empty() {
-            return Err(BuilderError::NoLm);
+            return error(BuilderError::NoLm);
         }
         if self.current_break.is_some() {
-            return Err(BuilderError::UnfinishedBreak);
+            return error(BuilderError::UnfinishedBreak);
         }
 
+        let date = self.date(previous);
         if let (Some(start_time), Some(end_time), Some(field), Some(date)) =
-            (&self.start_time, &self.end_time, &self.field, &self.date)
+            (&self.start_time, &self.end_time, &self.field, date)
         {
             let teff_minutes = if let Some(x) =
                 timestamp::effective_time_minutes(*start_time, *end_time, &self.breaks)
             {
                 x
             } else {
-                return Err(BuilderError::InvalidBreaks);
+                return error(BuilderError::InvalidBreaks);
             };
 
-            let lms = if let Some(x) =
-                checkpoints_to_durations(&self.limiting_magnitudes, *end_time, &self.breaks)
-            {
+            let lms = if let Some(x) = checkpoints_to_durations(
+                &LimitingMagnitude::values(&self.limiting_magnitudes),
+                *end_time,
+                &self.breaks,
+            ) {
                 x
             } else {
-                return Err(BuilderError::InvalidBreaks);
+                return error(BuilderError::InvalidBreaks);
             };
             let clouds =
                 if let Some(x) = checkpoints_to_durations(&self.clouds, *end_time, &self.breaks) {
                     x
                 } else {
-                    return Err(BuilderError::InvalidBreaks);
+                    return error(BuilderError::InvalidBreaks);
                 };
 
             let lm_avg = factors::limiting_magnitude(&lms);
             let lm_teff: u32 = lms.iter().map(|x| x.1).sum();
             if lm_teff != teff_minutes {
-                return Err(BuilderError::LmInsufficientTeff);
+                return error(BuilderError::LmInsufficientTeff);
             }
 
             let cloud_factor = factors::cloud_factor(&clouds);
             let f_teff: u32 = clouds.iter().map(|x| x.1).sum();
             if f_teff != teff_minutes {
-                return Err(BuilderError::FInsufficientTeff);
+                return error(BuilderError::FInsufficientTeff);
             }
 
             Ok(Period {
                 start_time: *start_time,
                 end_time: *end_time,
-                date: date.to_owned(),
+                date,
                 teff: f64::from(teff_minutes) / 60_f64,
                 limiting_magnitude: lm_avg,
                 field: *field,
+                // A location stays valid for the next periods until a new one is given.
+                location: self.location.or_else(|| previous.and_then(|p| p.location)),
                 cloud_factor,
+                checkpoints: Checkpoints {
+                    limiting_magnitudes: self.limiting_magnitudes.clone(),
+                    clouds: self.clouds.clone(),
+                    breaks: self.breaks.clone(),
+                },
                 showers: self.showers.unwrap_or_else(|| vec![]),
                 meteors: self.meteors,
             })
@@ -200,19 +637,167 @@ impl IncompletePeriod {
                 &self.start_time.is_none(),
                 &self.end_time.is_none(),
                 &self.field.is_none(),
-                &self.date.is_none(),
+                &date.is_none(),
             ) {
-                (true, _, _, _) => Err(BuilderError::NoStartTime),
-                (_, true, _, _) => Err(BuilderError::NoEndTime),
-                (_, _, true, _) => Err(BuilderError::NoField),
-                (_, _, _, true) => Err(BuilderError::NoDate),
-                _ => Err(BuilderError::Unknown),
+                (true, _, _, _) => error(BuilderError::NoStartTime),
+                (_, true, _, _) => error(BuilderError::NoEndTime),
+                (_, _, true, _) => error(BuilderError::NoField),
+                (_, _, _, true) => error(BuilderError::NoDate),
+                _ => error(BuilderError::Unknown),
+            }
+        }
+    }
+
+    fn locate(&self, error: BuilderError, number: usize, line: Option<usize>) -> LocatedError {
+        let lines = &self.lines;
+        let mut labels = vec![];
+        let mut label = |line: Option<usize>, message: String| {
+            if let Some(line) = line {
+                labels.push(Label { line, message });
+            }
+        };
+        let time = |time: Option<Timestamp>| match time {
+            Some(t) => format!(" at {}", t),
+            None => String::new(),
+        };
+        let ends_here = if line.is_some() {
+            format!("period {} ends here", number)
+        } else {
+            String::new()
+        };
+
+        match error {
+            BuilderError::NoStartTime
+            | BuilderError::NoEndTime
+            | BuilderError::NoLm
+            | BuilderError::NoField
+            | BuilderError::NoF
+            | BuilderError::NoDate
+            | BuilderError::Unknown => {
+                label(lines.first, format!("period {} begins here", number));
+                label(line, ends_here);
+            }
+            BuilderError::AlreadyDate
+            | BuilderError::AlreadyField
+            | BuilderError::AlreadyShowers
+            | BuilderError::AlreadyLocation => {
+                let first = match error {
+                    BuilderError::AlreadyDate => lines.date,
+                    BuilderError::AlreadyField => lines.field,
+                    BuilderError::AlreadyShowers => lines.showers,
+                    _ => lines.location,
+                };
+                label(first, "first given here".to_owned());
+                label(line, "given again here".to_owned());
+            }
+            BuilderError::AlreadySite => {
+                label(line, "given again here".to_owned());
+            }
+            BuilderError::InvalidLm => {
+                label(
+                    line,
+                    "no limiting magnitude follows from these areas".to_owned(),
+                );
+            }
+            BuilderError::InBreak => {
+                label(
+                    lines.current_break,
+                    format!("break starts here{}", time(self.current_break)),
+                );
+                label(line, "this happens during the break".to_owned());
+            }
+            BuilderError::NoBreakToEnd => {
+                label(line, "no break was started".to_owned());
+            }
+            BuilderError::UnfinishedBreak => {
+                label(
+                    lines.current_break,
+                    format!("break starts here{}", time(self.current_break)),
+                );
+                label(line, ends_here);
+            }
+            BuilderError::InvalidBreaks => {
+                label(
+                    lines.start,
+                    format!("period starts{}", time(self.start_time)),
+                );
+                label(lines.end, format!("period ends{}", time(self.end_time)));
+                for (b, l) in self.breaks.iter().zip(&lines.breaks) {
+                    let inside = match (self.start_time, self.end_time) {
+                        (Some(start), Some(end)) => {
+                            b.0.is_between(start, end) && b.1.is_between(start, end)
+                        }
+                        _ => false,
+                    };
+                    if !inside {
+                        label(Some(l.0), format!("break starts at {}", b.0));
+                        label(Some(l.1), format!("break ends at {}", b.1));
+                    }
+                }
+            }
+            BuilderError::LmInsufficientTeff | BuilderError::FInsufficientTeff => {
+                let (first, what) = if let BuilderError::LmInsufficientTeff = error {
+                    (
+                        self.limiting_magnitudes.first().map(|lm| lm.1),
+                        "limiting magnitude",
+                    )
+                } else {
+                    (self.clouds.first().map(|c| c.1), "cloud estimate")
+                };
+                let first_line = if let BuilderError::LmInsufficientTeff = error {
+                    lines.limiting_magnitudes.first().copied()
+                } else {
+                    lines.clouds.first().copied()
+                };
+                label(
+                    lines.start,
+                    format!("period starts{}", time(self.start_time)),
+                );
+                label(first_line, format!("first {}{}", what, time(first)));
+            }
+            BuilderError::NotObservingShower => {
+                label(lines.showers, "observed showers are given here".to_owned());
+                label(line, "this meteor is not one of them".to_owned());
+            }
+            BuilderError::ShowerNotActive => {
+                label(lines.date, "date given here".to_owned());
+                label(lines.showers, "observed showers are given here".to_owned());
+                if lines.date.is_none() {
+                    label(lines.start, "date follows from this start time".to_owned());
+                }
+            }
+            BuilderError::NotCorrected => {
+                label(line, "these areas are counted here".to_owned());
+            }
+        }
+
+        // Several labels on the same line are merged into the first one.
+        labels.sort_by_key(|l| l.line);
+        labels.dedup_by_key(|l| l.line);
+
+        let mut notes = vec![];
+        if let (Some(start), Some(end)) = (self.start_time, self.end_time) {
+            notes.push(format!("period {} runs from {} to {}", number, start, end));
+        }
+        for b in &self.breaks {
+            if let BuilderError::InvalidBreaks = error {
+                notes.push(format!("break from {} to {}", b.0, b.1));
             }
         }
+        if line.is_none() {
+            notes.push("this was found at the end of the input".to_owned());
+        }
+
+        LocatedError {
+            error,
+            period: number,
+            labels,
+            notes,
+        }
     }
 }
 
-fn checkpoints_to_durations<T>(
+pub fn checkpoints_to_durations<T>(
     cs: &[(T, Timestamp)],
     end: Timestamp,
     breaks: &[(Timestamp, Timestamp)],
@@ -250,6 +835,8 @@ pub enum BuilderError {
     AlreadyDate,
     AlreadyField,
     AlreadyShowers,
+    AlreadyLocation,
+    AlreadySite,
     InvalidLm,
     InBreak,
     NoBreakToEnd,
@@ -258,6 +845,8 @@ pub enum BuilderError {
     LmInsufficientTeff,
     FInsufficientTeff,
     NotObservingShower,
+    ShowerNotActive,
+    NotCorrected,
     Unknown,
 }
 
@@ -276,6 +865,8 @@ impl std::fmt::Display for BuilderError {
                 Self::AlreadyDate => "You already specified a date for this period.",
                 Self::AlreadyField => "You already specified a field for this period.",
                 Self::AlreadyShowers => "You already specified showers for this period.",
+                Self::AlreadyLocation => "You already specified a location for this period.",
+                Self::AlreadySite => "You already specified the site for this file.",
                 Self::InvalidLm => "Invalid data for calculating limiting magnitude.",
                 Self::InBreak => "You can't register events during a break.",
                 Self::NoBreakToEnd => "There is no ongoing break to end.",
@@ -290,6 +881,10 @@ impl std::fmt::Display for BuilderError {
                 Self::NotObservingShower => {
                     "Meteor belongs to a shower that you are not observing."
                 }
+                Self::ShowerNotActive =>
+                    "You are observing a shower that is not active on this date.",
+                Self::NotCorrected =>
+                    "The limiting magnitude of these areas cannot be corrected for extinction.",
                 Self::Unknown => "unexpected error",
             }
         )
@@ -298,9 +893,121 @@ impl std::fmt::Display for BuilderError {
 
 impl std::error::Error for BuilderError {}
 
+// A builder error with the period and the input lines that caused it.
+#[derive(Debug, Clone)]
+pub struct LocatedError {
+    pub error: BuilderError,
+    pub period: usize,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl LocatedError {
+    pub fn into_diagnostic(self) -> Diagnostic {
+        Diagnostic {
+            message: format!("{} (period {})", self.error, self.period),
+            labels: self.labels,
+            notes: self.notes,
+        }
+    }
+}
+
+impl std::fmt::Display for LocatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.clone().into_diagnostic())
+    }
+}
+
+impl std::error::Error for LocatedError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tally {
+    pub period: usize,
+    pub teff: f64,
+    // The last recorded limiting magnitude and cloud factor, not the averages.
+    pub limiting_magnitude: Option<f64>,
+    pub cloud_factor: Option<f64>,
+    pub counts: Vec<(Shower, u32)>,
+}
+
+impl std::fmt::Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let or_dash = |value: Option<f64>| match value {
+            Some(v) => format!("{:.2}", v),
+            None => "-".to_owned(),
+        };
+        write!(
+            f,
+            "Period {} | Teff {:.2} h | Lm {} | F {} |",
+            self.period,
+            self.teff,
+            or_dash(self.limiting_magnitude),
+            or_dash(self.cloud_factor)
+        )?;
+        for (shower, count) in &self.counts {
+            write!(f, " {} {}", shower.to_imo_code(), count)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuilderWarning {
+    ShowerNotActive(Shower, Date),
+    // The area, its altitude, the minimum altitude and the line of the count.
+    AreaTooLow(Area, f64, f64, usize),
+    // Why the Lm of the count on the line is not corrected for extinction.
+    NotCorrected(Uncorrected, usize),
+}
+
+impl std::fmt::Display for BuilderWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ShowerNotActive(shower, date) => write!(
+                f,
+                "{} is not active on {}, according to the shower catalog.",
+                shower.to_imo_code(),
+                date
+            ),
+            Self::AreaTooLow(area, altitude, min_altitude, line) => write!(
+                f,
+                "Area {} is {:.0}° high, below the minimum of {:.0}° (line {}).",
+                area.0, altitude, min_altitude, line
+            ),
+            Self::NotCorrected(reason, line) => write!(
+                f,
+                "The Lm of line {} is not corrected for extinction: {}.",
+                line, reason
+            ),
+        }
+    }
+}
+
+// Why the limiting magnitude of counted areas cannot be corrected for extinction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Uncorrected {
+    NoCentre(Area),
+    NoDate,
+    NoLocation,
+    // All counted areas are below the horizon.
+    BelowHorizon,
+}
+
+impl std::fmt::Display for Uncorrected {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NoCentre(area) => write!(f, "the centre of area {} is not known", area.0),
+            Self::NoDate => write!(f, "the date is not known"),
+            Self::NoLocation => write!(f, "the location is not known"),
+            Self::BelowHorizon => write!(f, "all areas are below the horizon"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::meteor::MeteorAttributes;
 
     #[test]
     fn test_ctd_1() {
@@ -537,7 +1244,7 @@ mod tests {
 
     #[test]
     fn test_builder_1() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -547,28 +1254,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -577,6 +1291,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -586,13 +1301,17 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 8))
             .unwrap();
 
         let session = builder.into_session().unwrap();
@@ -608,7 +1327,7 @@ mod tests {
 
     #[test]
     fn test_builder_2() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -618,28 +1337,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -648,6 +1374,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -657,16 +1384,20 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 8))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::NewPeriod))
+            .register_event(TimestampedEvent(end, Event::NewPeriod, 9))
             .unwrap();
 
         let start2 = Timestamp {
@@ -675,22 +1406,28 @@ mod tests {
         };
         let end2 = Timestamp { hour: 2, minute: 0 };
         builder
-            .register_event(TimestampedEvent(start2, Event::PeriodStart))
+            .register_event(TimestampedEvent(start2, Event::PeriodStart, 10))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start2,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                11,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start2,
                 Event::AreasCounted(vec![(11, Area(14))]),
+                12,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start2, Event::Clouds(5)))
+            .register_event(TimestampedEvent(start2, Event::Clouds(5), 13))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -699,10 +1436,11 @@ mod tests {
                     ra: 336.0,
                     dec: 52.3,
                 }),
+                14,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end2, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end2, Event::PeriodEnd, 15))
             .unwrap();
 
         let session = builder.into_session().unwrap();
@@ -728,7 +1466,7 @@ mod tests {
 
     #[test]
     fn test_builder_3() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -738,22 +1476,28 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 4))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -762,6 +1506,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -771,24 +1516,31 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 7))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoLm) => {}
+            Err(LocatedError {
+                error: BuilderError::NoLm,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoLm"),
         };
     }
 
     #[test]
     fn test_builder_4() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -798,24 +1550,31 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
@@ -825,6 +1584,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -834,24 +1594,31 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 7))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoF) => {}
+            Err(LocatedError {
+                error: BuilderError::NoF,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoF"),
         };
     }
 
     #[test]
     fn test_builder_5() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -863,23 +1630,30 @@ mod tests {
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                1,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                3,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 4))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -888,6 +1662,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -897,51 +1672,65 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 7))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoStartTime) => {}
+            Err(LocatedError {
+                error: BuilderError::NoStartTime,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoStartTime"),
         };
     }
 
     #[test]
     fn test_builder_6() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -950,6 +1739,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -959,21 +1749,28 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoEndTime) => {}
+            Err(LocatedError {
+                error: BuilderError::NoEndTime,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoEndTime"),
         };
     }
 
     #[test]
     fn test_builder_7() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -983,28 +1780,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1013,24 +1817,31 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 7))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoField) => {}
+            Err(LocatedError {
+                error: BuilderError::NoField,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoField"),
         };
     }
 
     #[test]
     fn test_builder_8() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1040,28 +1851,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1070,6 +1888,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1079,15 +1898,20 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 1 },
                 Event::BreakStart,
+                8,
             ))
             .unwrap();
         builder
@@ -1097,10 +1921,11 @@ mod tests {
                     minute: 31,
                 },
                 Event::BreakEnd,
+                9,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 10))
             .unwrap();
 
         let session = builder.into_session().unwrap();
@@ -1116,34 +1941,41 @@ mod tests {
 
     #[test]
     fn test_builder_9() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1152,6 +1984,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1161,29 +1994,38 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 1 },
                 Event::BreakStart,
+                8,
             ))
             .unwrap();
         match builder.register_event(TimestampedEvent(
             Timestamp { hour: 0, minute: 2 },
             Event::Clouds(5),
+            9,
         )) {
-            Err(BuilderError::InBreak) => {}
+            Err(LocatedError {
+                error: BuilderError::InBreak,
+                ..
+            }) => {}
             _ => panic!("register_event didn't return InBreak"),
         };
     }
 
     #[test]
     fn test_builder_10() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1193,28 +2035,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14)), (10, Area(7)), (8, Area(6))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1223,6 +2072,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1232,25 +2082,31 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 0 },
                 Event::AreasCounted(vec![(12, Area(14)), (12, Area(7)), (10, Area(6))]),
+                8,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 0 },
                 Event::Clouds(15),
+                9,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 10))
             .unwrap();
 
         let session = builder.into_session().unwrap();
@@ -1266,7 +2122,7 @@ mod tests {
 
     #[test]
     fn test_builder_11() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1276,28 +2132,35 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14)), (10, Area(7)), (8, Area(6))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1306,6 +2169,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1315,9 +2179,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         builder
@@ -1327,6 +2195,7 @@ mod tests {
                     minute: 15,
                 },
                 Event::BreakStart,
+                8,
             ))
             .unwrap();
         builder
@@ -1336,22 +2205,25 @@ mod tests {
                     minute: 55,
                 },
                 Event::BreakEnd,
+                9,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 0 },
                 Event::AreasCounted(vec![(12, Area(14)), (12, Area(7)), (10, Area(6))]),
+                10,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 0, minute: 0 },
                 Event::Clouds(15),
+                11,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 12))
             .unwrap();
 
         let session = builder.into_session().unwrap();
@@ -1367,34 +2239,41 @@ mod tests {
 
     #[test]
     fn test_builder_12() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1403,6 +2282,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1412,9 +2292,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         match builder.register_event(TimestampedEvent(
@@ -1423,42 +2307,53 @@ mod tests {
                 minute: 57,
             },
             Event::Field(Field { ra: 0.0, dec: 0.0 }),
+            8,
         )) {
-            Err(BuilderError::AlreadyField) => {}
+            Err(LocatedError {
+                error: BuilderError::AlreadyField,
+                ..
+            }) => {}
             _ => panic!("register_event does not return AlreadyField"),
         };
     }
 
     #[test]
     fn test_builder_13() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1467,6 +2362,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1476,9 +2372,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         match builder.register_event(TimestampedEvent(
@@ -1486,43 +2386,54 @@ mod tests {
                 hour: 22,
                 minute: 57,
             },
-            Event::Showers(vec![Shower::Leonids]),
+            Event::Showers(vec![Shower(*b"LEO")]),
+            8,
         )) {
-            Err(BuilderError::AlreadyShowers) => {}
+            Err(LocatedError {
+                error: BuilderError::AlreadyShowers,
+                ..
+            }) => {}
             _ => panic!("register_event does not return AlreadyShowers"),
         };
     }
 
     #[test]
     fn test_builder_14() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1531,6 +2442,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1540,9 +2452,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                7,
             ))
             .unwrap();
         match builder.register_event(TimestampedEvent(
@@ -1551,18 +2467,25 @@ mod tests {
                 minute: 57,
             },
             Event::Meteor(Meteor {
-                shower: Shower::KappaCygnids,
+                shower: Shower(*b"KCG"),
                 magnitude: 20,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
             }),
+            8,
         )) {
-            Err(BuilderError::NotObservingShower) => {}
+            Err(LocatedError {
+                error: BuilderError::NotObservingShower,
+                ..
+            }) => {}
             _ => panic!("register_event does not return NotObservingShower"),
         };
     }
 
     #[test]
     fn test_builder_15() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1572,22 +2495,28 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 4))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1596,6 +2525,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -1605,9 +2535,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1617,21 +2551,25 @@ mod tests {
                     minute: 57,
                 },
                 Event::AreasCounted(vec![(10, Area(14))]),
+                7,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 8))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::LmInsufficientTeff) => {}
+            Err(LocatedError {
+                error: BuilderError::LmInsufficientTeff,
+                ..
+            }) => {}
             _ => panic!("into_session does not return LmInsufficientTeff"),
         }
     }
 
     #[test]
     fn test_builder_16() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1641,24 +2579,31 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                4,
             ))
             .unwrap();
         builder
@@ -1668,6 +2613,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -1677,9 +2623,13 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1689,21 +2639,25 @@ mod tests {
                     minute: 57,
                 },
                 Event::Clouds(0),
+                7,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 8))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::FInsufficientTeff) => {}
+            Err(LocatedError {
+                error: BuilderError::FInsufficientTeff,
+                ..
+            }) => {}
             _ => panic!("into_session does not return FInsufficientTeff"),
         }
     }
 
     #[test]
     fn test_builder_17() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
@@ -1713,22 +2667,24 @@ mod tests {
             minute: 20,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(10, Area(14))]),
+                3,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 4))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1737,6 +2693,7 @@ mod tests {
                     ra: 290.0,
                     dec: 55.0,
                 }),
+                5,
             ))
             .unwrap();
         builder
@@ -1746,82 +2703,110 @@ mod tests {
                     minute: 57,
                 },
                 Event::Meteor(Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 35,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 }),
+                6,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(end, Event::PeriodEnd))
+            .register_event(TimestampedEvent(end, Event::PeriodEnd, 7))
             .unwrap();
 
         match builder.into_session() {
-            Err(BuilderError::NoDate) => {}
+            Err(LocatedError {
+                error: BuilderError::NoDate,
+                ..
+            }) => {}
             _ => panic!("into_session does not return NoDate"),
         };
     }
 
     #[test]
     fn test_builder_18() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 22,
             minute: 55,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("12 Aug 2019".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                3,
             ))
             .unwrap();
         match builder.register_event(TimestampedEvent(
             start,
-            Event::PeriodDate("13 Aug 2019".to_owned()),
+            Event::PeriodDate(Date {
+                year: 2019,
+                month: 8,
+                day: 13,
+            }),
+            4,
         )) {
-            Err(BuilderError::AlreadyDate) => {}
+            Err(LocatedError {
+                error: BuilderError::AlreadyDate,
+                ..
+            }) => {}
             _ => panic!("register_event does not return AlreadyDate"),
         };
     }
 
     #[test]
     fn test_builder_19() {
-        let mut builder = SessionBuilder::new();
+        let mut builder = SessionBuilder::new(Catalog::bundled());
         let start = Timestamp {
             hour: 0,
             minute: 30,
         };
         builder
-            .register_event(TimestampedEvent(start, Event::PeriodStart))
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::Showers(vec![Shower::Perseids]),
+                Event::Showers(vec![Shower(*b"PER")]),
+                2,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
-                Event::PeriodDate("7 Aug 2020".to_owned()),
+                Event::PeriodDate(Date {
+                    year: 2020,
+                    month: 8,
+                    day: 7,
+                }),
+                3,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 start,
                 Event::AreasCounted(vec![(8, Area(14))]),
+                4,
             ))
             .unwrap();
         builder
-            .register_event(TimestampedEvent(start, Event::Clouds(0)))
+            .register_event(TimestampedEvent(start, Event::Clouds(0), 5))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
@@ -1830,6 +2815,7 @@ mod tests {
                     ra: 315.0,
                     dec: 30.0,
                 }),
+                6,
             ))
             .unwrap();
         builder
@@ -1839,6 +2825,7 @@ mod tests {
                     minute: 46,
                 },
                 Event::BreakStart,
+                7,
             ))
             .unwrap();
         builder
@@ -1848,6 +2835,7 @@ mod tests {
                     minute: 52,
                 },
                 Event::BreakEnd,
+                8,
             ))
             .unwrap();
         builder
@@ -1857,14 +2845,244 @@ mod tests {
                     minute: 52,
                 },
                 Event::AreasCounted(vec![(7, Area(14))]),
+                9,
             ))
             .unwrap();
         builder
             .register_event(TimestampedEvent(
                 Timestamp { hour: 2, minute: 0 },
                 Event::PeriodEnd,
+                10,
             ))
             .unwrap();
         builder.into_session().unwrap();
     }
+
+    #[test]
+    fn test_builder_20() {
+        let mut builder = SessionBuilder::new(Catalog::bundled());
+        let start = Timestamp {
+            hour: 22,
+            minute: 55,
+        };
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::Showers(vec![Shower(*b"PER"), Shower(*b"GEM"), Shower(*b"SPO")]),
+                1,
+            ))
+            .unwrap();
+        assert!(builder.warnings().is_empty());
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12,
+                }),
+                2,
+            ))
+            .unwrap();
+        assert_eq!(
+            builder.warnings(),
+            &[BuilderWarning::ShowerNotActive(
+                Shower(*b"GEM"),
+                Date {
+                    year: 2019,
+                    month: 8,
+                    day: 12
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_builder_21() {
+        let mut builder = SessionBuilder::new(Catalog::bundled());
+        builder.set_strict(true);
+        let start = Timestamp {
+            hour: 22,
+            minute: 55,
+        };
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::PeriodDate(Date {
+                    year: 2019,
+                    month: 3,
+                    day: 10,
+                }),
+                1,
+            ))
+            .unwrap();
+        match builder.register_event(TimestampedEvent(
+            start,
+            Event::Showers(vec![Shower(*b"PER")]),
+            2,
+        )) {
+            Err(LocatedError {
+                error: BuilderError::ShowerNotActive,
+                ..
+            }) => {}
+            _ => panic!("register_event does not return ShowerNotActive"),
+        }
+    }
+
+    #[test]
+    fn test_builder_22() {
+        let mut builder = SessionBuilder::new(Catalog::bundled());
+        let start = Timestamp {
+            hour: 23,
+            minute: 30,
+        };
+        assert_eq!(
+            builder.tally(None),
+            Tally {
+                period: 1,
+                teff: 0.0,
+                limiting_magnitude: None,
+                cloud_factor: None,
+                counts: vec![],
+            }
+        );
+        builder
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 1))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::Showers(vec![Shower(*b"PER"), Shower(*b"SPO")]),
+                2,
+            ))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::AreasCounted(vec![(10, Area(14))]),
+                3,
+            ))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(start, Event::Clouds(5), 4))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(
+                Timestamp { hour: 0, minute: 5 },
+                Event::Meteor(Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                }),
+                5,
+            ))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(
+                Timestamp {
+                    hour: 0,
+                    minute: 10,
+                },
+                Event::BreakStart,
+                6,
+            ))
+            .unwrap();
+
+        let tally = builder.tally(Some(Timestamp {
+            hour: 0,
+            minute: 20,
+        }));
+        assert_eq!(tally.teff, 40_f64 / 60_f64);
+        assert_eq!(tally.cloud_factor, Some(1.05));
+        assert!(tally.limiting_magnitude.is_some());
+        assert_eq!(
+            tally.counts,
+            vec![(Shower(*b"PER"), 1), (Shower(*b"SPO"), 0)]
+        );
+        assert!(tally.to_string().ends_with("| PER 1 SPO 0"));
+    }
+
+    #[test]
+    fn test_builder_23() {
+        let mut builder = SessionBuilder::new(Catalog::bundled());
+        let start = Timestamp {
+            hour: 22,
+            minute: 55,
+        };
+        let field = Field {
+            ra: 290.0,
+            dec: 55.0,
+        };
+        builder
+            .register_event(TimestampedEvent(start, Event::PeriodStart, 2))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(start, Event::Field(field), 4))
+            .unwrap();
+        match builder.register_event(TimestampedEvent(start, Event::Field(field), 9)) {
+            Err(e) => {
+                assert_eq!(e.period, 1);
+                assert_eq!(
+                    e.labels,
+                    vec![
+                        Label::new(4, "first given here"),
+                        Label::new(9, "given again here")
+                    ]
+                );
+            }
+            _ => panic!("register_event does not return AlreadyField"),
+        }
+
+        match builder.into_session() {
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "No cloud information given for this period. (period 1) (line 2)"
+                );
+                assert_eq!(
+                    e.notes,
+                    vec!["this was found at the end of the input".to_owned()]
+                );
+            }
+            _ => panic!("into_session does not return NoF"),
+        }
+    }
+
+    #[test]
+    fn test_builder_24() {
+        let mut builder = SessionBuilder::new(Catalog::bundled());
+        let start = Timestamp {
+            hour: 22,
+            minute: 55,
+        };
+        let seen = Timestamp {
+            hour: 23,
+            minute: 10,
+        };
+        builder
+            .register_event(TimestampedEvent(
+                start,
+                Event::Showers(vec![Shower(*b"PER")]),
+                3,
+            ))
+            .unwrap();
+        builder
+            .register_event(TimestampedEvent(
+                seen,
+                Event::Meteor(Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                }),
+                7,
+            ))
+            .unwrap();
+        let meteor = &builder.current.meteors[0];
+        assert_eq!(meteor.time, Some(seen));
+        assert_eq!(meteor.line, Some(7));
+    }
 }
@@ -0,0 +1,289 @@
+use crate::lua::RESERVED_NAMES;
+use crate::meteor::Shower;
+use crate::timestamp;
+
+// The shower list bundled with Meteoraid: radiant positions, drifts, velocities
+// and population indices from the IMO working list.
+static BUNDLED_CATALOG: &str = include_str!("../data/showers.csv");
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Radiant {
+    pub ra: f64,
+    pub dec: f64,
+    // Drift of the radiant in degrees per degree of solar longitude.
+    pub ra_drift: f64,
+    pub dec_drift: f64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ShowerInfo {
+    pub shower: Shower,
+    pub name: String,
+    // First and last day of activity as (month, day).
+    pub activity: Option<((u32, u32), (u32, u32))>,
+    pub peak_solar_longitude: Option<f64>,
+    pub radiant: Option<Radiant>,
+    pub velocity: Option<f64>,
+    pub population_index: Option<f64>,
+}
+
+impl ShowerInfo {
+    // Showers without an activity window (sporadics, antihelion source) are always active.
+    pub fn is_active_on(&self, month: u32, day: u32) -> bool {
+        match self.activity {
+            Some((start, end)) if start <= end => start <= (month, day) && (month, day) <= end,
+            Some((start, end)) => start <= (month, day) || (month, day) <= end,
+            None => true,
+        }
+    }
+
+    // Radiant position at the given solar longitude, taking the drift into account.
+    pub fn radiant_at(&self, solar_longitude: f64) -> Option<(f64, f64)> {
+        let radiant = self.radiant?;
+        let offset = match self.peak_solar_longitude {
+            Some(peak) => (solar_longitude - peak + 180_f64).rem_euclid(360_f64) - 180_f64,
+            None => 0_f64,
+        };
+        Some((
+            (radiant.ra + radiant.ra_drift * offset).rem_euclid(360_f64),
+            radiant.dec + radiant.dec_drift * offset,
+        ))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Catalog {
+    showers: Vec<ShowerInfo>,
+}
+
+impl Catalog {
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_CATALOG).expect("[supposedly unreachable] Invalid bundled catalog")
+    }
+
+    // Parses a catalog with the same semicolon-separated format as data/showers.csv.
+    // The first line is a header. Empty fields mean "unknown".
+    pub fn parse(data: &str) -> Result<Self, CatalogError> {
+        let mut showers = vec![];
+        for (num, line) in data.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: &str| CatalogError {
+                line: num + 1,
+                message: message.to_owned(),
+            };
+
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            if fields.len() != 11 {
+                return Err(error("Expected 11 fields."));
+            }
+            let number = |i: usize| -> Result<Option<f64>, CatalogError> {
+                if fields[i].is_empty() {
+                    Ok(None)
+                } else {
+                    fields[i]
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| error("Invalid number."))
+                }
+            };
+
+            let shower = Shower::from_imo_code(fields[0])
+                .ok_or_else(|| error("Shower codes must consist of three uppercase letters."))?;
+            let function = fields[0].to_lowercase();
+            if RESERVED_NAMES.contains(&function.as_str()) {
+                return Err(error(&format!(
+                    "The shower code cannot be {}: {} is already used in the input.",
+                    fields[0], function
+                )));
+            }
+            let activity = match (fields[2], fields[3]) {
+                ("", "") => None,
+                (start, end) => Some((
+                    parse_month_day(start).ok_or_else(|| error("Invalid activity start."))?,
+                    parse_month_day(end).ok_or_else(|| error("Invalid activity end."))?,
+                )),
+            };
+            let radiant = match (number(5)?, number(6)?) {
+                (Some(ra), Some(dec)) => Some(Radiant {
+                    ra,
+                    dec,
+                    ra_drift: number(7)?.unwrap_or(0_f64),
+                    dec_drift: number(8)?.unwrap_or(0_f64),
+                }),
+                (None, None) => None,
+                _ => return Err(error("A radiant needs both RA and Dec.")),
+            };
+
+            showers.push(ShowerInfo {
+                shower,
+                name: fields[1].to_owned(),
+                activity,
+                peak_solar_longitude: number(4)?,
+                radiant,
+                velocity: number(9)?,
+                population_index: number(10)?,
+            });
+        }
+        Ok(Self { showers })
+    }
+
+    // Adds the showers of the other catalog, replacing those with the same code.
+    pub fn merge(&mut self, other: Catalog) {
+        for info in other.showers {
+            match self.showers.iter_mut().find(|s| s.shower == info.shower) {
+                Some(existing) => *existing = info,
+                None => self.showers.push(info),
+            }
+        }
+    }
+
+    pub fn get(&self, shower: Shower) -> Option<&ShowerInfo> {
+        self.showers.iter().find(|s| s.shower == shower)
+    }
+
+    pub fn showers(&self) -> &[ShowerInfo] {
+        &self.showers
+    }
+}
+
+fn parse_month_day(s: &str) -> Option<(u32, u32)> {
+    let mut parts = s.split_whitespace();
+    let month = timestamp::parse_month(parts.next()?)?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if parts.next().is_some() || day == 0 || day > 31 {
+        return None;
+    }
+    Some((month, day))
+}
+
+#[derive(Debug, Clone)]
+pub struct CatalogError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let catalog = Catalog::bundled();
+        let perseids = catalog.get(Shower(*b"PER")).unwrap();
+        assert_eq!(perseids.name, "Perseids");
+        assert_eq!(perseids.activity, Some(((7, 17), (8, 24))));
+        assert_eq!(perseids.peak_solar_longitude, Some(140.0));
+        assert_eq!(perseids.velocity, Some(59.0));
+        assert_eq!(perseids.population_index, Some(2.2));
+
+        let sporadic = catalog.get(Shower::SPORADIC).unwrap();
+        assert_eq!(sporadic.radiant, None);
+        assert_eq!(sporadic.activity, None);
+    }
+
+    #[test]
+    fn test_parse_catalog_1() {
+        let catalog = Catalog::parse(
+            "CODE;NAME;START;END;PEAK;RA;DEC;DRA;DDEC;V;R
+NDA;Northern delta-Aquariids;Jul 15;Aug 25;139;335;-5;;;42;3.4
+
+ANT;Antihelion Source;;;;;;;;30;3.0",
+        )
+        .unwrap();
+        assert_eq!(catalog.showers().len(), 2);
+        let nda = catalog.get(Shower(*b"NDA")).unwrap();
+        assert_eq!(
+            nda.radiant,
+            Some(Radiant {
+                ra: 335.0,
+                dec: -5.0,
+                ra_drift: 0.0,
+                dec_drift: 0.0
+            })
+        );
+        assert_eq!(catalog.get(Shower(*b"PER")), None);
+    }
+
+    #[test]
+    fn test_parse_catalog_2() {
+        let header = "CODE;NAME;START;END;PEAK;RA;DEC;DRA;DDEC;V;R\n";
+        let e = Catalog::parse(&(header.to_owned() + "nda;x;;;;;;;;;")).unwrap_err();
+        assert_eq!(e.line, 2);
+        assert!(Catalog::parse(&(header.to_owned() + "NDA;x;;;;;;;;")).is_err());
+        assert!(Catalog::parse(&(header.to_owned() + "NDA;x;Jul 15;;;;;;;;")).is_err());
+        assert!(Catalog::parse(&(header.to_owned() + "NDA;x;;;;335;;;;;")).is_err());
+        assert!(Catalog::parse(&(header.to_owned() + "NDA;x;;;;abc;3;;;;")).is_err());
+        let e = Catalog::parse(&(header.to_owned() + "SQM;x;;;;;;;;;")).unwrap_err();
+        assert_eq!(
+            e.message,
+            "The shower code cannot be SQM: sqm is already used in the input."
+        );
+        assert!(Catalog::parse(&(header.to_owned() + "END;x;;;;;;;;;")).is_err());
+    }
+
+    #[test]
+    fn test_merge_catalog() {
+        let mut catalog = Catalog::bundled();
+        let count = catalog.showers().len();
+        catalog.merge(
+            Catalog::parse(
+                "CODE;NAME;START;END;PEAK;RA;DEC;DRA;DDEC;V;R
+PER;Perseids;Jul 17;Aug 24;140.0;48;58;1.4;0.26;59;2.6
+NDA;Northern delta-Aquariids;Jul 15;Aug 25;139;335;-5;;;42;3.4",
+            )
+            .unwrap(),
+        );
+        assert_eq!(catalog.showers().len(), count + 1);
+        assert_eq!(
+            catalog.get(Shower(*b"PER")).unwrap().population_index,
+            Some(2.6)
+        );
+    }
+
+    #[test]
+    fn test_is_active_on() {
+        let catalog = Catalog::bundled();
+        let perseids = catalog.get(Shower(*b"PER")).unwrap();
+        assert!(perseids.is_active_on(8, 12));
+        assert!(perseids.is_active_on(7, 17));
+        assert!(perseids.is_active_on(8, 24));
+        assert!(!perseids.is_active_on(8, 25));
+        assert!(!perseids.is_active_on(3, 10));
+
+        let quadrantids = catalog.get(Shower(*b"QUA")).unwrap();
+        assert!(quadrantids.is_active_on(12, 30));
+        assert!(quadrantids.is_active_on(1, 4));
+        assert!(!quadrantids.is_active_on(1, 13));
+        assert!(!quadrantids.is_active_on(12, 27));
+
+        assert!(catalog.get(Shower::SPORADIC).unwrap().is_active_on(3, 10));
+    }
+
+    #[test]
+    fn test_radiant_at() {
+        let catalog = Catalog::bundled();
+        let perseids = catalog.get(Shower(*b"PER")).unwrap();
+        assert_eq!(perseids.radiant_at(140.0), Some((48.0, 58.0)));
+        let (ra, dec) = perseids.radiant_at(130.0).unwrap();
+        assert_eq!((ra.round(), (dec * 10.0).round() / 10.0), (34.0, 55.4));
+
+        let catalog = Catalog::parse(
+            "CODE;NAME;START;END;PEAK;RA;DEC;DRA;DDEC;V;R
+XYZ;Test;;;359;359.5;10;1;-1;;",
+        )
+        .unwrap();
+        let xyz = catalog.get(Shower(*b"XYZ")).unwrap();
+        assert_eq!(xyz.radiant_at(1.0), Some((1.5, 8.0)));
+        assert_eq!(xyz.radiant_at(358.0), Some((358.5, 11.0)));
+    }
+}
@@ -0,0 +1,161 @@
+// An error or warning that points at lines of the input file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(line: usize, message: &str) -> Self {
+        Self {
+            line,
+            message: message.to_owned(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_owned(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn with_label(mut self, line: usize, message: &str) -> Self {
+        self.labels.push(Label::new(line, message));
+        self
+    }
+
+    pub fn lines(&self) -> Vec<usize> {
+        let mut lines: Vec<usize> = self.labels.iter().map(|l| l.line).collect();
+        lines.sort();
+        lines.dedup();
+        lines
+    }
+
+    // Renders the diagnostic like rustc does: the labelled source lines with carets under
+    // their code, followed by the notes.
+    pub fn render(&self, kind: &str, file_name: &str, source: &[String]) -> String {
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|l| l.line);
+        let gutter = labels
+            .iter()
+            .map(|l| l.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty_gutter = " ".repeat(gutter);
+
+        let mut out = vec![format!("{}: {}", kind, self.message)];
+        if let Some(first) = labels.first() {
+            out.push(format!("{}--> {}:{}", empty_gutter, file_name, first.line));
+            out.push(format!("{} |", empty_gutter));
+        }
+        let mut notes = vec![];
+        let mut shown = false;
+        for label in labels {
+            let code = match source.get(label.line.wrapping_sub(1)) {
+                Some(code) => code,
+                None => {
+                    notes.push(format!("line {}: {}", label.line, label.message));
+                    continue;
+                }
+            };
+            shown = true;
+            let indent = code.len() - code.trim_start().len();
+            let code_len = code.split("--").next().unwrap_or("").trim().len().max(1);
+            out.push(format!("{:>width$} | {}", label.line, code, width = gutter));
+            out.push(
+                format!(
+                    "{} | {}{} {}",
+                    empty_gutter,
+                    " ".repeat(indent),
+                    "^".repeat(code_len),
+                    label.message
+                )
+                .trim_end()
+                .to_owned(),
+            );
+        }
+        notes.extend(self.notes.iter().cloned());
+        if !notes.is_empty() {
+            if shown || self.labels.is_empty() {
+                out.push(format!("{} |", empty_gutter));
+            }
+            for note in notes {
+                out.push(format!("{} = note: {}", empty_gutter, note));
+            }
+        }
+        out.join("\n")
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        let lines = self.lines();
+        if !lines.is_empty() {
+            let lines: Vec<String> = lines.iter().map(usize::to_string).collect();
+            write!(
+                f,
+                " (line{} {})",
+                if lines.len() == 1 { "" } else { "s" },
+                lines.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let source: Vec<String> = vec![
+            "2114".to_owned(),
+            "period_start".to_owned(),
+            "  clouds(0) << 2120 -- late".to_owned(),
+        ];
+        let mut diagnostic = Diagnostic::new("Something is wrong.")
+            .with_label(3, "cloud estimate at 21:20")
+            .with_label(2, "period starts at 21:14");
+        diagnostic.notes.push("period 1 runs from 21:14".to_owned());
+        assert_eq!(
+            diagnostic.render("error", "input.txt", &source),
+            "error: Something is wrong.
+ --> input.txt:2
+  |
+2 | period_start
+  | ^^^^^^^^^^^^ period starts at 21:14
+3 |   clouds(0) << 2120 -- late
+  |   ^^^^^^^^^^^^^^^^^ cloud estimate at 21:20
+  |
+  = note: period 1 runs from 21:14"
+        );
+        assert_eq!(diagnostic.to_string(), "Something is wrong. (lines 2, 3)");
+    }
+
+    #[test]
+    fn test_render_missing_line() {
+        let diagnostic = Diagnostic::new("Something is wrong.").with_label(5, "here");
+        assert_eq!(
+            diagnostic.render("error", "input.txt", &[]),
+            "error: Something is wrong.
+ --> input.txt:5
+  |
+  = note: line 5: here"
+        );
+    }
+}
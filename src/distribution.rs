@@ -44,14 +44,17 @@ impl Distribution {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::meteor::Shower;
+    use crate::meteor::{MeteorAttributes, Shower};
 
     #[test]
     fn test_distr_1() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 20,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(distr.0, vec![0, 0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0]);
         let map = distr.to_map();
@@ -62,8 +65,11 @@ mod tests {
     fn test_distr_2() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 15,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(distr.0, vec![0, 0, 0, 0, 0, 0, 0, 5, 5, 0, 0, 0, 0, 0]);
         let map = distr.to_map();
@@ -75,8 +81,11 @@ mod tests {
     fn test_distr_3() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: -15,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(distr.0, vec![0, 0, 0, 0, 5, 5, 0, 0, 0, 0, 0, 0, 0, 0]);
         let map = distr.to_map();
@@ -88,8 +97,11 @@ mod tests {
     fn test_distr_4() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 75,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(distr.0, vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10]);
         let map = distr.to_map();
@@ -100,8 +112,11 @@ mod tests {
     fn test_distr_5() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: -65,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(distr.0, vec![10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
         let map = distr.to_map();
@@ -112,44 +127,74 @@ mod tests {
     fn test_distr_6() {
         let mut distr = Distribution::new();
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 30,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 30,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 10,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 25,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: -15,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 50,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 40,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 45,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: 0,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         distr.add_meteor(Meteor {
-            shower: Shower::Perseids,
+            shower: Shower(*b"PER"),
             magnitude: -5,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
         });
         assert_eq!(
             distr.0,
@@ -0,0 +1,102 @@
+use crate::meteor::Meteor;
+use crate::session::Session;
+use crate::timestamp::DateTime;
+
+// A meteor of magnitude -3 or brighter, with what is needed for the IMO fireball report.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fireball {
+    pub meteor: Meteor,
+    // The date and time it was seen, or only the date of its period if the time is unknown.
+    pub seen: Option<DateTime>,
+    pub period: usize,
+}
+
+impl Session {
+    pub fn fireballs(&self) -> Vec<Fireball> {
+        let mut fireballs = vec![];
+        for (i, period) in self.periods.iter().enumerate() {
+            for meteor in period.meteors.iter().filter(|m| m.is_fireball()) {
+                fireballs.push(Fireball {
+                    meteor: meteor.clone(),
+                    seen: meteor.time.map(|t| period.resolve(t)),
+                    period: i + 1,
+                });
+            }
+        }
+        fireballs
+    }
+
+    // The fireballs in the order of the IMO fireball report form: who saw it and where, when,
+    // how bright, which shower, and what else was noted. The magnitude is the one that was
+    // logged, also when it is brighter than the -6 of the distribution.
+    pub fn get_fireball_csv(&self) -> String {
+        let who_and_where = self
+            .header
+            .csv_fields()
+            .unwrap_or_else(|| ";;;;".to_owned());
+
+        let mut lines = vec![
+            "OBSERVER;SITE;LAT;LON;ELEV;DATE UT;TIME UT;PERIOD;SHOWER;MAG;TRAIN;COLOR;NOTE;LINE"
+                .to_owned(),
+        ];
+        for fireball in self.fireballs() {
+            let meteor = &fireball.meteor;
+            let (date, time) = match fireball.seen {
+                Some(seen) => (seen.date.to_string(), seen.time.to_string()),
+                None => (
+                    self.periods[fireball.period - 1].date.to_string(),
+                    "-".to_owned(),
+                ),
+            };
+            lines.push(format!(
+                "{};{};{};{};{};{:.1};{};{}",
+                who_and_where,
+                date,
+                time,
+                fireball.period,
+                meteor.shower.to_imo_code(),
+                f64::from(meteor.magnitude) / 10_f64,
+                meteor.attributes.csv_fields(),
+                match meteor.line {
+                    Some(line) => line.to_string(),
+                    None => "-".to_owned(),
+                }
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+
+    #[test]
+    fn test_fireballs() {
+        let session = Interpreter::test_session(
+            "observer(\"DANTH\")
+             site(\"Gent; Belgium\", 51.05, 3.72, 10)
+             2350
+             period_start
+             date(\"12 Aug 2019\")
+             showers(PER, SPO)
+             fieldC(336, 52.3)
+             clouds(0)
+             areas(area14(10))
+             per(-2.5)
+             spo(-3) << 2355
+             per(-8, {train=12, color=\"green\", note=\"ended in a flare, moving north\"}) << 10
+             period_end << 30",
+        );
+        let fireballs = session.fireballs();
+        assert_eq!(fireballs.len(), 2);
+        assert_eq!(fireballs[1].meteor.magnitude, -80);
+        assert_eq!(fireballs[1].period, 1);
+        assert_eq!(
+            session.get_fireball_csv(),
+            "OBSERVER;SITE;LAT;LON;ELEV;DATE UT;TIME UT;PERIOD;SHOWER;MAG;TRAIN;COLOR;NOTE;LINE
+DANTH;Gent, Belgium;51.05;3.72;10;12 Aug 2019;23:55;1;SPO;-3.0;;;;11
+DANTH;Gent, Belgium;51.05;3.72;10;13 Aug 2019;00:10;1;PER;-8.0;12;green;ended in a flare, moving north;12"
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use crate::field::Field;
+use crate::meteor::{Meteor, MeteorAttributes, Shower};
+use crate::session::{Checkpoints, LimitingMagnitude, LmMethod, Period, Session, SessionHeader};
+use crate::timestamp::{Date, Timestamp};
+use std::collections::HashMap;
+
+const DISTRIBUTION_HEADER: &str = "DATE UT;START;END;SHOWER;-6;-5;-4;-3;-2;-1;0;1;2;3;4;5;6;7";
+
+// Reads a count CSV and a distribution CSV in the IMO format (as written by
+// Session::get_csvs) back into a session. The individual meteors are
+// reconstructed from the magnitude distribution.
+pub fn import_session(count_csv: &str, distr_csv: &str) -> Result<Session, ImportError> {
+    let mut distributions = parse_distribution_csv(distr_csv)?;
+    let mut periods = vec![];
+
+    let mut lines = count_csv.lines().enumerate();
+    let header = match lines.next() {
+        Some((_, h)) => h,
+        None => return Err(ImportError::new(CsvFile::Count, 1, "The file is empty.")),
+    };
+    let showers = parse_count_header(header)?;
+
+    for (num, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |message: &str| ImportError::new(CsvFile::Count, num + 1, message);
+
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        if fields.len() != 8 + 2 * showers.len() {
+            return Err(error(&format!(
+                "Expected {} columns, found {}.",
+                8 + 2 * showers.len(),
+                fields.len()
+            )));
+        }
+        let number = |i: usize, name: &str| -> Result<f64, ImportError> {
+            fields[i]
+                .parse::<f64>()
+                .map_err(|_| error(&format!("Invalid number for {}.", name)))
+        };
+
+        let (date, start_time, end_time) = parse_period_key(&fields[..3], error)?;
+        let limiting_magnitude = number(7, "Lm")?;
+        let cloud_factor = number(6, "F")?;
+        let mut period = Period {
+            start_time,
+            end_time,
+            date,
+            teff: number(3, "Teff")?,
+            limiting_magnitude,
+            field: Field {
+                ra: number(4, "RA")?,
+                dec: number(5, "Dec")?,
+            },
+            location: None,
+            cloud_factor,
+            // Only the averages are known, so they are taken to hold for the whole period.
+            checkpoints: Checkpoints {
+                limiting_magnitudes: vec![(
+                    LimitingMagnitude {
+                        value: limiting_magnitude,
+                        method: LmMethod::Imported,
+                    },
+                    start_time,
+                )],
+                clouds: vec![(clouds_from_factor(cloud_factor), start_time)],
+                breaks: vec![],
+            },
+            showers: vec![],
+            meteors: vec![],
+        };
+
+        for (i, shower) in showers.iter().enumerate() {
+            let count = match (fields[8 + 2 * i], fields[9 + 2 * i]) {
+                ("C", n) => n
+                    .parse::<u32>()
+                    .map_err(|_| error(&format!("Invalid count for {}.", shower.to_imo_code())))?,
+                ("-", "") => continue,
+                _ => {
+                    return Err(error(&format!(
+                        "Expected C;<count> or -; for {}.",
+                        shower.to_imo_code()
+                    )))
+                }
+            };
+            period.showers.push(*shower);
+
+            let meteors = match distributions.remove(&(date, start_time, end_time, *shower)) {
+                Some((distr_num, halves)) => {
+                    let meteors = meteors_from_distribution(*shower, halves).ok_or_else(|| {
+                        ImportError::new(
+                            CsvFile::Distribution,
+                            distr_num,
+                            "The distribution can't be made from whole and half magnitudes.",
+                        )
+                    })?;
+                    if meteors.len() != count as usize {
+                        return Err(error(&format!(
+                            "The count for {} does not match the distribution on line {}.",
+                            shower.to_imo_code(),
+                            distr_num
+                        )));
+                    }
+                    meteors
+                }
+                None if count == 0 => vec![],
+                None => {
+                    return Err(error(&format!(
+                        "No distribution found for {}.",
+                        shower.to_imo_code()
+                    )))
+                }
+            };
+            period.meteors.extend(meteors);
+        }
+        periods.push(period);
+    }
+
+    if let Some((distr_num, _)) = distributions.values().min_by_key(|(n, _)| *n) {
+        return Err(ImportError::new(
+            CsvFile::Distribution,
+            *distr_num,
+            "This line does not belong to any period of the count CSV.",
+        ));
+    }
+
+    Ok(Session {
+        header: SessionHeader::default(),
+        periods,
+    })
+}
+
+// The inverse of factors::cloud_factor for a single estimate.
+fn clouds_from_factor(cloud_factor: f64) -> u8 {
+    match (1_f64 - 1_f64 / cloud_factor) * 100_f64 {
+        clouds if clouds <= 0_f64 => 0,
+        clouds => clouds.round().min(100_f64) as u8,
+    }
+}
+
+fn parse_count_header(header: &str) -> Result<Vec<Shower>, ImportError> {
+    let error = |message: &str| ImportError::new(CsvFile::Count, 1, message);
+    let fields: Vec<&str> = header.split(';').map(str::trim).collect();
+    if fields.len() < 8
+        || fields[..8] != ["DATE UT", "START", "END", "Teff", "RA", "Dec", "F", "Lm"]
+    {
+        return Err(error("Invalid header."));
+    }
+
+    let mut showers = vec![];
+    for (i, code) in fields[8..].iter().enumerate() {
+        if i % 2 == 1 {
+            if !code.is_empty() {
+                return Err(error("Shower codes must be separated by two semicolons."));
+            }
+        } else {
+            showers.push(
+                Shower::from_imo_code(code)
+                    .ok_or_else(|| error(&format!("Invalid shower code {}.", code)))?,
+            );
+        }
+    }
+    Ok(showers)
+}
+
+type DistributionKey = (Date, Timestamp, Timestamp, Shower);
+
+// Maps every (date, start, end, shower) to its line number and the
+// distribution in halves of meteors, from -6 to 7.
+fn parse_distribution_csv(
+    distr_csv: &str,
+) -> Result<HashMap<DistributionKey, (usize, Vec<u32>)>, ImportError> {
+    let mut map = HashMap::new();
+    let mut lines = distr_csv.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == DISTRIBUTION_HEADER => {}
+        _ => {
+            return Err(ImportError::new(
+                CsvFile::Distribution,
+                1,
+                "Invalid header.",
+            ))
+        }
+    }
+
+    for (num, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let error = |message: &str| ImportError::new(CsvFile::Distribution, num + 1, message);
+
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        if fields.len() != 18 {
+            return Err(error(&format!(
+                "Expected 18 columns, found {}.",
+                fields.len()
+            )));
+        }
+        let (date, start_time, end_time) = parse_period_key(&fields[..3], error)?;
+        let shower = Shower::from_imo_code(fields[3])
+            .ok_or_else(|| error(&format!("Invalid shower code {}.", fields[3])))?;
+
+        let mut halves = vec![];
+        for field in &fields[4..] {
+            let value = field
+                .parse::<f64>()
+                .map_err(|_| error(&format!("Invalid number {}.", field)))?;
+            let doubled = value * 2_f64;
+            if value < 0_f64 || doubled.fract() != 0_f64 {
+                return Err(error("Distribution values must be multiples of 0.5."));
+            }
+            halves.push(doubled as u32);
+        }
+
+        if map
+            .insert((date, start_time, end_time, shower), (num + 1, halves))
+            .is_some()
+        {
+            return Err(error("Duplicate line for this period and shower."));
+        }
+    }
+    Ok(map)
+}
+
+fn parse_period_key(
+    fields: &[&str],
+    error: impl Fn(&str) -> ImportError,
+) -> Result<(Date, Timestamp, Timestamp), ImportError> {
+    let date = Date::parse(fields[0]).ok_or_else(|| error("Invalid DATE UT."))?;
+    let time = |i: usize, name: &str| match fields[i].parse::<u32>() {
+        Ok(n) if n / 100 < 24 && n % 100 < 60 => Ok(Timestamp::from_shorthand_int_notation(n)),
+        _ => Err(error(&format!("Invalid {} time {}.", name, fields[i]))),
+    };
+    Ok((date, time(1, "START")?, time(2, "END")?))
+}
+
+// Half counts in a magnitude class come from meteors halfway between two classes,
+// so they are paired with the next class. Two such meteors give the same
+// distribution as two whole-magnitude meteors, so this choice does not matter.
+fn meteors_from_distribution(shower: Shower, mut halves: Vec<u32>) -> Option<Vec<Meteor>> {
+    let mut meteors = vec![];
+    for i in 0..halves.len() {
+        let magnitude = (i as i32 - 6) * 10;
+        while halves[i] >= 2 {
+            meteors.push(Meteor {
+                shower,
+                magnitude,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
+            });
+            halves[i] -= 2;
+        }
+        if halves[i] == 1 {
+            if halves.get(i + 1).copied().unwrap_or(0) == 0 {
+                return None;
+            }
+            meteors.push(Meteor {
+                shower,
+                magnitude: magnitude + 5,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
+            });
+            halves[i] -= 1;
+            halves[i + 1] -= 1;
+        }
+    }
+    Some(meteors)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvFile {
+    Count,
+    Distribution,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportError {
+    pub file: CsvFile,
+    pub line: usize,
+    pub message: String,
+}
+
+impl ImportError {
+    fn new(file: CsvFile, line: usize, message: &str) -> Self {
+        Self {
+            file,
+            line,
+            message: message.to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} CSV, line {}: {}",
+            match self.file {
+                CsvFile::Count => "Count",
+                CsvFile::Distribution => "Distribution",
+            },
+            self.line,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNT_CSV: &str = "DATE UT;START;END;Teff;RA;Dec;F;Lm;ANT;;KCG;;PER;;SPO
+12 Aug 2019;2330;30;1.0000;336;52.3;1.0500;5.5200;-;;C;0;C;7;C;3
+13 Aug 2019;130;200;0.5000;298;56;1.0800;5.9100;C;0;C;0;-;;C;3";
+
+    const DISTR_CSV: &str = "DATE UT;START;END;SHOWER;-6;-5;-4;-3;-2;-1;0;1;2;3;4;5;6;7
+12 Aug 2019;2330;30;KCG;0;0;0;0;0;0;0;0;0;0;0;0;0;0
+12 Aug 2019;2330;30;PER;0;0;0;0;0;0.5;0.5;0;1;5;0;0;0;0
+12 Aug 2019;2330;30;SPO;0;0;0;0.5;0.5;0;0;0;0;0;1;1;0;0
+13 Aug 2019;130;200;ANT;0;0;0;0;0;0;0;0;0;0;0;0;0;0
+13 Aug 2019;130;200;KCG;0;0;0;0;0;0;0;0;0;0;0;0;0;0
+13 Aug 2019;130;200;SPO;0;0;0;0;0;1;0.5;0.5;0;1;0;0;0;0";
+
+    #[test]
+    fn test_import_1() {
+        let session = import_session(COUNT_CSV, DISTR_CSV).unwrap();
+        assert_eq!(session.periods.len(), 2);
+        let period = &session.periods[0];
+        assert_eq!(period.date.to_string(), "12 Aug 2019");
+        assert_eq!(
+            period.end_time,
+            Timestamp {
+                hour: 0,
+                minute: 30
+            }
+        );
+        assert_eq!(period.teff, 1.0);
+        assert_eq!(period.cloud_factor, 1.05);
+        assert_eq!(period.checkpoints.clouds, vec![(5, period.start_time)]);
+        assert_eq!(period.limiting_magnitude, 5.52);
+        assert_eq!(
+            period.field,
+            Field {
+                ra: 336.0,
+                dec: 52.3
+            }
+        );
+        assert_eq!(
+            period.showers,
+            vec![Shower(*b"KCG"), Shower(*b"PER"), Shower(*b"SPO")]
+        );
+        assert_eq!(period.meteors.len(), 10);
+    }
+
+    #[test]
+    fn test_import_roundtrip() {
+        let session = import_session(COUNT_CSV, DISTR_CSV).unwrap();
+        let (count_csv, distr_csv) = session.get_csvs().unwrap();
+        assert_eq!(count_csv, COUNT_CSV);
+        assert_eq!(distr_csv, DISTR_CSV);
+    }
+
+    #[test]
+    fn test_meteors_from_distribution() {
+        let per = Shower(*b"PER");
+        let meteors =
+            meteors_from_distribution(per, vec![0, 0, 0, 0, 0, 1, 1, 0, 2, 10, 0, 0, 0, 0])
+                .unwrap();
+        let magnitudes: Vec<i32> = meteors.iter().map(|m| m.magnitude).collect();
+        assert_eq!(magnitudes, vec![-5, 20, 30, 30, 30, 30, 30]);
+
+        assert_eq!(
+            meteors_from_distribution(per, vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]),
+            None
+        );
+    }
+
+    fn import_error(count_csv: &str, distr_csv: &str) -> ImportError {
+        match import_session(count_csv, distr_csv) {
+            Ok(_) => panic!("import should have failed"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let e = import_error("DATE UT;START;END;Teff;RA;Dec;F", DISTR_CSV);
+        assert_eq!((e.file, e.line), (CsvFile::Count, 1));
+
+        let e = import_error(&COUNT_CSV.replace("1.0500", "abc"), DISTR_CSV);
+        assert_eq!((e.file, e.line), (CsvFile::Count, 2));
+        assert_eq!(e.to_string(), "Count CSV, line 2: Invalid number for F.");
+
+        let e = import_error(&COUNT_CSV.replace("C;7", "C;8"), DISTR_CSV);
+        assert_eq!((e.file, e.line), (CsvFile::Count, 2));
+
+        let e = import_error(COUNT_CSV, &DISTR_CSV.replace(";5;0;0;0;0", ";5.2;0;0;0;0"));
+        assert_eq!((e.file, e.line), (CsvFile::Distribution, 3));
+
+        let e = import_error(
+            COUNT_CSV,
+            &(DISTR_CSV.to_owned() + "\n14 Aug 2019;130;200;SPO;0;0;0;0;0;0;0;0;0;0;0;0;0;0"),
+        );
+        assert_eq!((e.file, e.line), (CsvFile::Distribution, 8));
+
+        let e = import_error(&COUNT_CSV.replace("C;0;-;;C;3", "C;0;C;2;C;3"), DISTR_CSV);
+        assert_eq!(
+            e.to_string(),
+            "Count CSV, line 3: No distribution found for PER."
+        );
+
+        let e = import_error(
+            &COUNT_CSV.replace("2330;30;1.0000", "2575;30;1.0000"),
+            DISTR_CSV,
+        );
+        assert_eq!(e.to_string(), "Count CSV, line 2: Invalid START time 2575.");
+
+        let e = import_error(COUNT_CSV, &DISTR_CSV.replace("130;200;ANT", "130;1299;ANT"));
+        assert_eq!(
+            e.to_string(),
+            "Distribution CSV, line 5: Invalid END time 1299."
+        );
+    }
+}
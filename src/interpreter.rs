@@ -1,25 +1,96 @@
-use crate::builder::{BuilderError, SessionBuilder};
+use crate::areas::{AreaCentres, LmExplanation};
+use crate::builder::{BuilderWarning, LocatedError, SessionBuilder, Tally};
+use crate::catalog::Catalog;
+use crate::diagnostic::Diagnostic;
+use crate::lint::{LintWarning, Linter};
+use crate::location::Site;
 use crate::lua;
 use crate::session::*;
+use crate::sqm::SqmConversion;
 use crate::timestamp::Timestamp;
 use rlua::Lua;
 
 pub struct Interpreter {
-    session_builder: SessionBuilder,
+    catalog: Catalog,
+    strict: bool,
+    sqm_conversion: SqmConversion,
+    area_check: Option<(AreaCentres, f64)>,
+    extinction: Option<f64>,
+    // Several observers can log in the same file. Each has their own periods, so the
+    // periods of one observer continue when `observer` switches back to them.
+    observers: Vec<ObserverState>,
+    current: usize,
+    site: Option<Site>,
     time_checkpoint: Option<Timestamp>,
     lua: Lua,
+    // The number of lines executed so far, which is the line number of the last line.
+    line: usize,
+    // Whether the last line was a new_period, so that a new period has started.
+    at_new_period: bool,
+    // The builder warnings of all observers, in the order of the lines that caused them.
+    warnings: Vec<BuilderWarning>,
+}
+
+struct ObserverState {
+    builder: SessionBuilder,
+    linter: Linter,
+    // Set by `recover`: lines are ignored until the next new_period.
+    recovering: bool,
+    // How many warnings of the builder are already in the interpreter's warnings.
+    warnings_seen: usize,
+}
+
+impl ObserverState {
+    fn new(catalog: &Catalog, linter: Linter) -> Self {
+        Self {
+            builder: SessionBuilder::new(catalog.clone()),
+            linter,
+            recovering: false,
+            warnings_seen: 0,
+        }
+    }
 }
 
 impl Interpreter {
-    pub fn new() -> Result<Self, rlua::Error> {
+    pub fn new(catalog: &Catalog) -> Result<Self, rlua::Error> {
         Ok(Self {
-            session_builder: SessionBuilder::new(),
+            catalog: catalog.clone(),
+            strict: false,
+            sqm_conversion: SqmConversion::default(),
+            area_check: None,
+            extinction: None,
+            observers: vec![ObserverState::new(catalog, Linter::default())],
+            current: 0,
+            site: None,
             time_checkpoint: None,
-            lua: lua::new_lua()?,
+            lua: lua::new_lua(catalog)?,
+            line: 0,
+            at_new_period: false,
+            warnings: vec![],
+        })
+    }
+
+    pub fn execute_one_line(&mut self, line: &str) -> Result<(), Diagnostic> {
+        self.line += 1;
+        self.at_new_period = false;
+        for observer in &mut self.observers {
+            observer.linter.read_directive(self.line, line);
+        }
+        let result = self.execute(line);
+        self.collect_warnings();
+        result.map_err(|e| {
+            let mut diagnostic = match e.downcast::<LocatedError>() {
+                Ok(located) => located.into_diagnostic(),
+                Err(e) => Diagnostic::new(&e.to_string()).with_label(self.line, ""),
+            };
+            if let (true, Some(observer)) = (self.observers.len() > 1, self.observer()) {
+                diagnostic.notes.push(format!("observer {}", observer));
+            }
+            diagnostic
         })
     }
 
-    pub fn execute_one_line(&mut self, line: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fn execute(&mut self, line: &str) -> Result<(), Box<dyn std::error::Error>> {
         let code = line
             .split("--")
             .next()
@@ -48,27 +119,292 @@ impl Interpreter {
             if let Some(exact_timestamp) = maybe_exact_timestamp {
                 self.time_checkpoint = Some(exact_timestamp);
             }
-            match self.time_checkpoint {
-                Some(time) => {
-                    let time_and_event = TimestampedEvent(time, lua::run_code(code, &self.lua)?);
-                    self.session_builder.register_event(time_and_event)?;
+            if self.observers[self.current].recovering {
+                match lua::run_code(code, &self.lua) {
+                    Ok(Event::NewPeriod) => {
+                        self.observers[self.current].recovering = false;
+                        return Ok(());
+                    }
+                    // The lines of the other observers are not skipped.
+                    Ok(Event::Observer(_)) => {}
+                    _ => return Ok(()),
+                }
+            }
+            let event = lua::run_code(code, &self.lua)?;
+            // The observer and site are not tied to a time, and can come before the first
+            // time checkpoint.
+            let header = event.is_header();
+            let time = match (self.time_checkpoint, header) {
+                (Some(time), _) => time,
+                (None, true) => Timestamp { hour: 0, minute: 0 },
+                (None, false) => return Err(Box::new(InterpreterError::NoTimeCheckpoint)),
+            };
+            self.at_new_period = event == Event::NewPeriod;
+            match event {
+                Event::Observer(code) => self.switch_observer(code, time)?,
+                Event::Site(site) => {
+                    for observer in &mut self.observers {
+                        observer.builder.register_event(TimestampedEvent(
+                            time,
+                            Event::Site(site.clone()),
+                            self.line,
+                        ))?;
+                    }
+                    self.site = Some(site);
+                }
+                _ => {
+                    let observer = &mut self.observers[self.current];
+                    observer.builder.register_event(TimestampedEvent(
+                        time,
+                        event.clone(),
+                        self.line,
+                    ))?;
+                    observer
+                        .linter
+                        .observe(&TimestampedEvent(time, event, self.line));
                 }
-                None => return Err(Box::new(InterpreterError::NoTimeCheckpoint)),
             }
         }
         Ok(())
     }
 
+    // The lines before the first `observer` belong to the first observer. A new observer
+    // gets the site that is already given.
+    fn switch_observer(
+        &mut self,
+        code: String,
+        time: Timestamp,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let existing = self
+            .observers
+            .iter()
+            .position(|o| o.builder.header().observer.as_ref() == Some(&code));
+        if let Some(index) = existing {
+            self.current = index;
+            return Ok(());
+        }
+        if self.observers[self.current]
+            .builder
+            .header()
+            .observer
+            .is_some()
+        {
+            let linter = self.observers[0].linter.without_observations();
+            let mut observer = ObserverState::new(&self.catalog, linter);
+            self.configure(&mut observer.builder);
+            if let Some(site) = &self.site {
+                observer.builder.register_event(TimestampedEvent(
+                    time,
+                    Event::Site(site.clone()),
+                    self.line,
+                ))?;
+            }
+            self.observers.push(observer);
+            self.current = self.observers.len() - 1;
+        }
+        self.observers[self.current]
+            .builder
+            .register_event(TimestampedEvent(time, Event::Observer(code), self.line))?;
+        Ok(())
+    }
+
+    // Runs the checks and corrections of the periods that end with the input. Call it after
+    // the last line, before reading the warnings and Lm explanations. An error in strict
+    // mode comes back from `into_results`.
+    pub fn finish(&mut self) {
+        for observer in &mut self.observers {
+            let _ = observer.builder.finish_checks();
+        }
+        self.collect_warnings();
+    }
+
+    fn collect_warnings(&mut self) {
+        for observer in &mut self.observers {
+            let new = &observer.builder.warnings()[observer.warnings_seen..];
+            self.warnings.extend_from_slice(new);
+            observer.warnings_seen += new.len();
+        }
+    }
+
     #[cfg(test)]
-    pub fn execute_multiple_lines(&mut self, code: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn execute_multiple_lines(&mut self, code: &str) -> Result<(), Diagnostic> {
         for line in code.split('\n') {
             self.execute_one_line(line)?;
         }
         Ok(())
     }
 
-    pub fn get_session(self) -> Result<Session, BuilderError> {
-        self.session_builder.into_session()
+    // The session of an input that has no errors, for the tests of the other modules.
+    #[cfg(test)]
+    pub fn test_session(code: &str) -> Session {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter.execute_multiple_lines(code).unwrap();
+        interpreter.get_sessions().unwrap().remove(0)
+    }
+
+    // Continues after an error: the period with the error is dropped, and the lines up to the
+    // next new_period are skipped. Errors on a new_period line already started a new period.
+    pub fn recover(&mut self) {
+        let observer = &mut self.observers[self.current];
+        observer.linter.reset_period();
+        if !self.at_new_period {
+            observer.builder.discard_period();
+            observer.recovering = true;
+        }
+    }
+
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+        self.configure_all();
+    }
+
+    // How `sqm` readings are converted to a limiting magnitude.
+    pub fn set_sqm_conversion(&mut self, sqm_conversion: SqmConversion) {
+        self.sqm_conversion = sqm_conversion;
+        self.configure_all();
+    }
+
+    // Warns about counted areas lower than the minimum altitude, given the area centres.
+    pub fn set_area_check(&mut self, centres: AreaCentres, min_altitude: f64) {
+        self.area_check = Some((centres, min_altitude));
+        self.configure_all();
+    }
+
+    // Corrects the limiting magnitude of counted areas for the extinction at their altitude,
+    // with this coefficient. This needs the area centres.
+    pub fn set_extinction(&mut self, k: f64) {
+        self.extinction = Some(k);
+        self.configure_all();
+    }
+
+    fn configure(&self, builder: &mut SessionBuilder) {
+        builder.set_strict(self.strict);
+        builder.set_sqm_conversion(self.sqm_conversion);
+        builder.set_area_check(self.area_check.clone());
+        builder.set_extinction(self.extinction);
+    }
+
+    fn configure_all(&mut self) {
+        let mut observers = std::mem::take(&mut self.observers);
+        for observer in &mut observers {
+            self.configure(&mut observer.builder);
+        }
+        self.observers = observers;
+    }
+
+    pub fn warnings(&self) -> &[BuilderWarning] {
+        &self.warnings
+    }
+
+    // How the limiting magnitude of every `areas` line was found, in the order of the lines.
+    pub fn lm_explanations(&self) -> Vec<&(usize, Timestamp, LmExplanation)> {
+        let mut explanations: Vec<&(usize, Timestamp, LmExplanation)> = self
+            .observers
+            .iter()
+            .flat_map(|o| o.builder.lm_explanations())
+            .collect();
+        explanations.sort_by_key(|e| e.0);
+        explanations
+    }
+
+    pub fn lints(&self) -> Vec<&LintWarning> {
+        self.observers
+            .iter()
+            .flat_map(|o| o.linter.warnings())
+            .collect()
+    }
+
+    // The observer that the next lines are for, if one is given.
+    pub fn observer(&self) -> Option<&str> {
+        self.observers[self.current]
+            .builder
+            .header()
+            .observer
+            .as_deref()
+    }
+
+    // The running tally of the current observer.
+    pub fn tally(&self) -> Tally {
+        self.observers[self.current]
+            .builder
+            .tally(self.time_checkpoint)
+    }
+
+    // The session or the error at the end of the file of every observer, in the order in
+    // which they first appear. The last period of an observer whose lines are being skipped
+    // after `recover` was discarded, so their session ends with the period before it.
+    pub fn into_results(self) -> Vec<Result<Session, Diagnostic>> {
+        let multiple = self.observers.len() > 1;
+        self.observers
+            .into_iter()
+            .map(|observer| {
+                if observer.recovering {
+                    return Ok(observer.builder.into_finished_session());
+                }
+                let name = observer.builder.header().observer.clone();
+                observer.builder.into_session().map_err(|e| {
+                    let mut diagnostic = e.into_diagnostic();
+                    if let (true, Some(name)) = (multiple, name) {
+                        diagnostic.notes.push(format!("observer {}", name));
+                    }
+                    diagnostic
+                })
+            })
+            .collect()
+    }
+
+    // For every observer, the numbers of the periods that `into_results` gives, as they are
+    // counted in the diagnostics.
+    pub fn period_numbers(&self) -> Vec<Vec<usize>> {
+        self.observers
+            .iter()
+            .map(|observer| {
+                let mut numbers = observer.builder.finished_period_numbers();
+                if !observer.recovering {
+                    numbers.push(observer.builder.period_number());
+                }
+                numbers
+            })
+            .collect()
+    }
+
+    // One session per observer, or the errors of all observers whose last period is invalid.
+    pub fn get_sessions(self) -> Result<Vec<Session>, Vec<Diagnostic>> {
+        let mut sessions = vec![];
+        let mut errors = vec![];
+        for result in self.into_results() {
+            match result {
+                Ok(session) => sessions.push(session),
+                Err(e) => errors.push(e),
+            }
+        }
+        if errors.is_empty() {
+            Ok(sessions)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// How the interpreter reads an input file, from the command-line arguments.
+#[derive(Default)]
+pub struct InputOptions {
+    pub strict: bool,
+    pub sqm_conversion: SqmConversion,
+    pub area_check: Option<(AreaCentres, f64)>,
+    pub extinction: Option<f64>,
+}
+
+impl InputOptions {
+    pub fn apply(&self, interpreter: &mut Interpreter) {
+        interpreter.set_strict(self.strict);
+        interpreter.set_sqm_conversion(self.sqm_conversion);
+        if let Some((centres, min_altitude)) = &self.area_check {
+            interpreter.set_area_check(centres.clone(), *min_altitude);
+        }
+        if let Some(k) = self.extinction {
+            interpreter.set_extinction(k);
+        }
     }
 }
 
@@ -94,10 +430,13 @@ impl std::error::Error for InterpreterError {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::areas::AreaVerdict;
+    use crate::builder::Uncorrected;
+    use crate::meteor::Shower;
 
     #[test]
     fn test_interpreter_1() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         interpreter
             .execute_multiple_lines(
                 "2237
@@ -111,7 +450,7 @@ mod tests {
                  period_end",
             )
             .unwrap();
-        let session = interpreter.get_session().unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
         let period = &session.periods[0];
         assert_eq!(period.teff, 1.0);
         assert_eq!(period.showers.len(), 4);
@@ -120,7 +459,7 @@ mod tests {
 
     #[test]
     fn test_interpreter_2() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         interpreter
             .execute_multiple_lines(
                 "period_start << 2237
@@ -132,7 +471,7 @@ mod tests {
                 period_end << 2337",
             )
             .unwrap();
-        let session = interpreter.get_session().unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
         let period = &session.periods[0];
         assert_eq!(period.teff, 1.0);
         assert_eq!(period.showers.len(), 4);
@@ -141,7 +480,7 @@ mod tests {
 
     #[test]
     fn test_interpreter_3() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         assert!(interpreter
             .execute_multiple_lines(
                 "period_start
@@ -157,7 +496,7 @@ mod tests {
 
     #[test]
     fn test_interpreter_4() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         interpreter
             .execute_multiple_lines(
                 "period_start << 2237
@@ -173,7 +512,7 @@ mod tests {
                  period_end << 2337",
             )
             .unwrap();
-        let session = interpreter.get_session().unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
         let period = &session.periods[0];
         assert_eq!(period.teff, 1.0);
         assert_eq!(period.showers.len(), 4);
@@ -184,7 +523,7 @@ mod tests {
 
     #[test]
     fn test_interpreter_5() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         interpreter
             .execute_multiple_lines(
                 "period_start << 2237
@@ -212,7 +551,7 @@ mod tests {
                  ",
             )
             .unwrap();
-        let session = interpreter.get_session().unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
         assert_eq!(session.periods.len(), 2);
         let period = &session.periods[0];
         assert_eq!(period.teff, 1.0);
@@ -227,7 +566,7 @@ mod tests {
 
     #[test]
     fn test_interpreter_6() {
-        let mut interpreter = Interpreter::new().unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
         interpreter
             .execute_multiple_lines(
                 "2237
@@ -241,6 +580,525 @@ mod tests {
                  period_end",
             )
             .unwrap();
-        assert!(interpreter.get_session().is_err());
+        assert!(interpreter.get_sessions().is_err());
+    }
+
+    #[test]
+    fn test_interpreter_7() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter
+            .execute_multiple_lines(
+                "period_start << 2237
+                 date(\"31 Aug 2019\")
+                 clouds(0)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 period_end << 2350
+
+                 new_period
+
+                 period_start << 0015
+                 clouds(0)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 period_end << 0030",
+            )
+            .unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert_eq!(session.periods[0].end().date.to_string(), "31 Aug 2019");
+        assert_eq!(session.periods[1].date.to_string(), "1 Sep 2019");
+    }
+
+    #[test]
+    fn test_interpreter_8() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter
+            .execute_multiple_lines(
+                "2237
+                 period_start
+                 date(\"12 Aug 2019\")
+                 clouds(0)
+                 showers(PER, ANT, KCG, SPO) << 2238
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 2337
+                 period_end",
+            )
+            .unwrap();
+        let mut errors = interpreter.get_sessions().err().unwrap();
+        assert_eq!(errors.len(), 1);
+        let diagnostic = errors.remove(0);
+        assert_eq!(
+            diagnostic.message,
+            "Your recorded limiting magnitudes do not span your whole period. (period 1)"
+        );
+        assert_eq!(diagnostic.lines(), vec![2, 6]);
+        assert_eq!(
+            diagnostic.labels[1].message,
+            "first limiting magnitude at 22:38"
+        );
+        assert!(diagnostic
+            .notes
+            .contains(&"period 1 runs from 22:37 to 23:37".to_owned()));
+    }
+
+    #[test]
+    fn test_interpreter_9() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let diagnostic = interpreter
+            .execute_multiple_lines(
+                "2237
+                 period_start
+                 -- a comment
+
+                 clouds(",
+            )
+            .err()
+            .unwrap();
+        assert_eq!(diagnostic.lines(), vec![5]);
+
+        let diagnostic = interpreter
+            .execute_multiple_lines("showers(PER)\nshowers(SPO)")
+            .err()
+            .unwrap();
+        assert_eq!(diagnostic.lines(), vec![6, 7]);
+    }
+
+    #[test]
+    fn test_interpreter_lm() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter
+            .execute_multiple_lines(
+                "2300
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area14(10))
+                 lm(6.2) << 2330
+                 period_end << 2400",
+            )
+            .unwrap();
+        interpreter.finish();
+        let explanations = interpreter.lm_explanations();
+        assert_eq!(explanations.len(), 1);
+        assert_eq!(explanations[0].0, 7);
+        assert_eq!(explanations[0].2.average, 5.58);
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        let period = &session.periods[0];
+        assert_eq!(
+            period
+                .checkpoints
+                .limiting_magnitudes
+                .iter()
+                .map(|c| (c.0.method, c.1))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    LmMethod::Areas,
+                    Timestamp::from_shorthand_int_notation(2300)
+                ),
+                (
+                    LmMethod::Estimate,
+                    Timestamp::from_shorthand_int_notation(2330)
+                ),
+            ]
+        );
+        assert!((period.limiting_magnitude - (5.58 + 6.2) / 2_f64).abs() < 0.01);
+        assert_eq!(
+            session.get_lm_csv(),
+            "DATE UT;TIME UT;PERIOD;Lm;METHOD;SQM
+12 Aug 2019;2300;1;5.58;areas;
+12 Aug 2019;2330;1;6.20;estimate;"
+        );
+    }
+
+    #[test]
+    fn test_interpreter_sqm() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter.set_sqm_conversion(SqmConversion { a: 7.0, b: 4.316 });
+        interpreter
+            .execute_multiple_lines(
+                "2300
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 sqm(21.35)
+                 period_end << 2400",
+            )
+            .unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        let period = &session.periods[0];
+        assert_eq!(
+            period.checkpoints.limiting_magnitudes[0].0.method,
+            LmMethod::Sqm(21.35)
+        );
+        assert!((period.limiting_magnitude - 5.38).abs() < 0.01);
+        assert_eq!(
+            session.get_lm_csv(),
+            "DATE UT;TIME UT;PERIOD;Lm;METHOD;SQM
+12 Aug 2019;2300;1;5.38;sqm;21.35"
+        );
+    }
+
+    #[test]
+    fn test_interpreter_area_check() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let centres = AreaCentres::parse("AREA;RA;DEC\n14;279.2;38.8\n7;0;-80").unwrap();
+        interpreter.set_area_check(centres, 30.0);
+        interpreter
+            .execute_multiple_lines(
+                "site(\"Gent\", 51.05, 3.72, 10)
+                 2200
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area14(11), area7(10), area2(8))
+                 period_end << 2300",
+            )
+            .unwrap();
+        interpreter.finish();
+        let warnings = interpreter.warnings();
+        assert_eq!(warnings.len(), 1);
+        match warnings[0] {
+            BuilderWarning::AreaTooLow(area, altitude, min_altitude, line) => {
+                assert_eq!(area.0, 7);
+                assert!(altitude < 0.0);
+                assert_eq!(min_altitude, 30.0);
+                assert_eq!(line, 8);
+            }
+            _ => panic!("expected a warning about area 7"),
+        }
+    }
+
+    #[test]
+    fn test_interpreter_area_check_periods() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let centres = AreaCentres::parse("AREA;RA;DEC\n14;279.2;38.8\n7;0;-80").unwrap();
+        interpreter.set_area_check(centres, 30.0);
+        // The second period takes its date and location from the first one, and the
+        // areas of the first one are counted before its date and location are given.
+        interpreter
+            .execute_multiple_lines(
+                "2200
+                 period_start
+                 areas(area7(10))
+                 date(\"12 Aug 2019\")
+                 location(51.05, 3.72, 10)
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 period_end << 2300
+                 new_period
+                 period_start << 2300
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area7(10))
+                 period_end << 2400",
+            )
+            .unwrap();
+        assert_eq!(interpreter.warnings().len(), 1);
+        interpreter.finish();
+        let lines: Vec<usize> = interpreter
+            .warnings()
+            .iter()
+            .map(|w| match w {
+                BuilderWarning::AreaTooLow(_, _, _, line) => *line,
+                _ => panic!("expected warnings about area 7"),
+            })
+            .collect();
+        assert_eq!(lines, vec![3, 15]);
+    }
+
+    #[test]
+    fn test_interpreter_extinction() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let centres = AreaCentres::parse("AREA;RA;DEC\n14;279.2;38.8").unwrap();
+        interpreter.set_area_check(centres, 30.0);
+        interpreter.set_extinction(0.3);
+        interpreter
+            .execute_multiple_lines(
+                "site(\"Gent\", 51.05, 3.72, 10)
+                 2200
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area14(11))
+                 period_end << 2300
+                 new_period
+                 period_start << 2300
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area14(11))
+                 period_end << 2400",
+            )
+            .unwrap();
+        interpreter.finish();
+        let explanations = interpreter.lm_explanations();
+        assert_eq!(explanations.len(), 2);
+        let area = &explanations[0].2.areas[0];
+        assert_eq!(area.limiting_magnitude, 5.64);
+        assert!(area.correction > 0.0 && area.correction < 0.05);
+        // The second period is corrected as well, with the date of the first one.
+        let area = &explanations[1].2.areas[0];
+        assert!(area.correction > 0.0 && area.correction < 0.05);
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert!(session.periods[0].limiting_magnitude > 5.64);
+        assert!(session.periods[1].limiting_magnitude > 5.64);
+    }
+
+    #[test]
+    fn test_interpreter_extinction_uncorrected() {
+        let centres = AreaCentres::parse("AREA;RA;DEC\n14;279.2;38.8\n7;0;-80").unwrap();
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter.set_area_check(centres.clone(), 0.0);
+        interpreter.set_extinction(0.3);
+        interpreter
+            .execute_multiple_lines(
+                "site(\"Gent\", 51.05, 3.72, 10)
+                 2200
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)
+                 fieldC(0, 0)
+                 clouds(0)
+                 areas(area14(11), area7(10))
+                 period_end << 2300",
+            )
+            .unwrap();
+        interpreter.finish();
+        // Area 7 is below the horizon, so only area 14 is left.
+        let explanation = &interpreter.lm_explanations()[0].2;
+        assert_eq!(explanation.areas[1].verdict, AreaVerdict::BelowHorizon);
+        assert!((explanation.average - explanation.areas[0].corrected()).abs() < 0.01);
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert!(session.periods[0].limiting_magnitude > 5.64);
+
+        let code = "2200
+                    period_start
+                    date(\"12 Aug 2019\")
+                    showers(SPO)
+                    fieldC(0, 0)
+                    clouds(0)
+                    areas(area14(11))
+                    period_end << 2300";
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter.set_area_check(centres.clone(), 0.0);
+        interpreter.set_extinction(0.3);
+        interpreter.execute_multiple_lines(code).unwrap();
+        interpreter.finish();
+        assert_eq!(
+            interpreter.warnings(),
+            &[BuilderWarning::NotCorrected(Uncorrected::NoLocation, 7)]
+        );
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert_eq!(session.periods[0].limiting_magnitude, 5.64);
+
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter.set_area_check(centres, 0.0);
+        interpreter.set_extinction(0.3);
+        interpreter.set_strict(true);
+        interpreter.execute_multiple_lines(code).unwrap();
+        interpreter.finish();
+        let errors = match interpreter.get_sessions() {
+            Err(errors) => errors,
+            Ok(_) => panic!("expected the areas to be refused"),
+        };
+        assert!(errors[0]
+            .message
+            .starts_with("The limiting magnitude of these areas cannot be corrected"));
+        assert_eq!(errors[0].labels[0].line, 7);
+        assert_eq!(errors[0].notes[0], "the location is not known");
+    }
+
+    #[test]
+    fn test_interpreter_recover() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let mut errors = vec![];
+        for line in "period_start << 2237
+                 date(\"12 Aug 2019\")
+                 showers(PER, SPO)
+                 per(3)
+                 kcg(2)
+                 spo(2)
+                 new_period
+                 period_start << 2340
+                 date(\"12 Aug 2019\")
+                 clouds(0)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 period_end << 2350
+                 new_period
+                 period_start << 2350
+                 areas(area14(10))
+                 showers(SPO)
+                 showers(SPO)
+                 clouds(0)"
+            .split('\n')
+        {
+            if let Err(e) = interpreter.execute_one_line(line) {
+                errors.push(e.lines());
+                interpreter.recover();
+            }
+        }
+        assert_eq!(errors, vec![vec![3, 5], vec![18, 19]]);
+        assert_eq!(interpreter.tally().period, 4);
+        assert_eq!(interpreter.period_numbers(), vec![vec![2]]);
+        // The Lm of the discarded period is not explained.
+        let explanations = interpreter.lm_explanations();
+        assert_eq!(explanations.len(), 1);
+        assert_eq!(explanations[0].0, 12);
+        // Only the second period is left; the third one was discarded.
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert_eq!(session.periods.len(), 1);
+        assert_eq!(
+            session.periods[0].start_time.to_shorthand_int_notation(),
+            2340
+        );
+    }
+
+    #[test]
+    fn test_interpreter_observers_errors() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        for line in "observer(\"AAAAA\")
+                     2200
+                     period_start
+                     date(\"12 Aug 2019\")
+                     showers(SPO)
+                     fieldC(0, 0)
+                     clouds(0)
+                     areas(area14(11))
+                     observer(\"BBBBB\")
+                     period_start
+                     showers(SPO)
+                     showers(SPO)
+                     observer(\"CCCCC\")
+                     period_start"
+            .split('\n')
+        {
+            if interpreter.execute_one_line(line).is_err() {
+                interpreter.recover();
+            }
+        }
+        // BBBBB is recovering, which does not hide the errors of the others.
+        let results = interpreter.into_results();
+        assert_eq!(results.len(), 3);
+        let notes: Vec<&Vec<String>> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .map(|e| &e.notes)
+            .collect();
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].contains(&"observer AAAAA".to_owned()));
+        assert!(notes[1].contains(&"observer CCCCC".to_owned()));
+        assert_eq!(results[1].as_ref().unwrap().periods.len(), 0);
+    }
+
+    #[test]
+    fn test_interpreter_header() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter
+            .execute_multiple_lines(
+                "observer(\"DANTH\")
+                 site(\"Gent\", 51.05, 3.72, 10)
+                 2237
+                 period_start
+                 date(\"12 Aug 2019\")
+                 clouds(0)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 period_end << 2337",
+            )
+            .unwrap();
+        let session = interpreter.get_sessions().unwrap().remove(0);
+        assert_eq!(session.header.observer, Some("DANTH".to_owned()));
+        let site = session.header.site.unwrap();
+        assert_eq!(site.name, "Gent");
+        assert_eq!(session.periods[0].location, Some(site.location));
+
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        let diagnostic = interpreter
+            .execute_multiple_lines(
+                "site(\"Gent\", 51.05, 3.72, 10)
+                 2237
+                 period_start
+                 site(\"Gent\", 51.05, 3.72, 10)",
+            )
+            .err()
+            .unwrap();
+        assert_eq!(
+            diagnostic.message,
+            "You already specified the site for this file. (period 1)"
+        );
+        assert_eq!(diagnostic.lines(), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_interpreter_observers() {
+        let mut interpreter = Interpreter::new(&Catalog::bundled()).unwrap();
+        interpreter
+            .execute_multiple_lines(
+                "2200
+                 observer(\"AAAAA\")
+                 period_start
+                 date(\"12 Aug 2019\")
+                 clouds(0)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(336, 52.3)
+                 observer(\"BBBBB\")
+                 period_start
+                 date(\"12 Aug 2019\")
+                 clouds(10)
+                 showers(PER, SPO)
+                 areas(area14(11))
+                 fieldC(300, 50)
+                 per(2) << 2210
+                 observer(\"AAAAA\")
+                 spo(3)
+                 period_end << 2300
+                 observer(\"BBBBB\")
+                 period_end << 2230",
+            )
+            .unwrap();
+        assert_eq!(interpreter.observer(), Some("BBBBB"));
+        assert_eq!(interpreter.tally().counts[0], (Shower(*b"PER"), 1));
+        assert!(interpreter.lints().is_empty());
+
+        let sessions = interpreter.get_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        let (a, b) = (&sessions[0].periods[0], &sessions[1].periods[0]);
+        assert_eq!(sessions[0].header.observer, Some("AAAAA".to_owned()));
+        assert_eq!(
+            a.end_time,
+            Timestamp {
+                hour: 23,
+                minute: 0
+            }
+        );
+        assert_eq!(a.meteors[0].shower, Shower::SPORADIC);
+        assert_eq!(sessions[1].header.observer, Some("BBBBB".to_owned()));
+        assert_eq!(
+            b.end_time,
+            Timestamp {
+                hour: 22,
+                minute: 30
+            }
+        );
+        assert_eq!(b.meteors[0].shower, Shower(*b"PER"));
+        assert_eq!(b.field.ra, 300.0);
     }
 }
@@ -0,0 +1,308 @@
+use crate::diagnostic::{Diagnostic, Label};
+use crate::session::{Event, TimestampedEvent};
+use crate::timestamp::{self, Timestamp};
+use std::collections::{HashMap, HashSet};
+
+// Things that are allowed in an input file, but are likely mistakes. The codes are
+// stable, so they can be used to allow a lint with a comment directive.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Lint {
+    TimeBackwards,
+    HourWithoutMeteors,
+    LongPeriod,
+    HeavyClouds,
+    MeteorAtPeriodEnd,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::TimeBackwards,
+        Lint::HourWithoutMeteors,
+        Lint::LongPeriod,
+        Lint::HeavyClouds,
+        Lint::MeteorAtPeriodEnd,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::TimeBackwards => "W001",
+            Self::HourWithoutMeteors => "W002",
+            Self::LongPeriod => "W003",
+            Self::HeavyClouds => "W004",
+            Self::MeteorAtPeriodEnd => "W005",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.code() == code)
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            Self::TimeBackwards => "The time goes backwards.",
+            Self::HourWithoutMeteors => "No meteors were seen for an hour or longer.",
+            Self::LongPeriod => "This period is longer than 2 hours.",
+            Self::HeavyClouds => "More than 20% of the field is covered by clouds.",
+            Self::MeteorAtPeriodEnd => "A meteor is logged in the same minute as the period end.",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    pub lint: Lint,
+    pub diagnostic: Diagnostic,
+}
+
+impl LintWarning {
+    fn new(lint: Lint, labels: Vec<Label>) -> Self {
+        Self {
+            lint,
+            diagnostic: Diagnostic {
+                message: lint.message().to_owned(),
+                labels,
+                notes: vec![],
+            },
+        }
+    }
+
+    // The line the warning is about, where a directive can allow it.
+    pub fn line(&self) -> Option<usize> {
+        self.diagnostic.labels.first().map(|l| l.line)
+    }
+
+    pub fn render(&self, file_name: &str, source: &[String]) -> String {
+        self.diagnostic
+            .render(&format!("warning[{}]", self.lint.code()), file_name, source)
+    }
+}
+
+// Looks at the events one by one, as they are registered.
+#[derive(Default)]
+pub struct Linter {
+    last: Option<(Timestamp, usize)>,
+    start: Option<(Timestamp, usize)>,
+    meteors: Vec<(Timestamp, usize)>,
+    breaks: Vec<(Timestamp, Timestamp)>,
+    current_break: Option<Timestamp>,
+    warnings: Vec<LintWarning>,
+    allowed: HashSet<Lint>,
+    allowed_on_line: HashMap<usize, HashSet<Lint>>,
+}
+
+impl Linter {
+    // Reads an "allow(W001, W004)" directive from the comment of a line. On a line with
+    // only a comment, it allows the lints in the whole file, otherwise only on that line.
+    pub fn read_directive(&mut self, line: usize, text: &str) {
+        let mut split = text.splitn(2, "--");
+        let code = split.next().unwrap_or("").trim();
+        let comment = match split.next() {
+            Some(c) => c.trim(),
+            None => return,
+        };
+        if !comment.starts_with("allow(") || !comment.ends_with(')') {
+            return;
+        }
+        let lints = comment["allow(".len()..comment.len() - 1]
+            .split(',')
+            .filter_map(|c| Lint::from_code(c.trim()));
+        if code.is_empty() {
+            self.allowed.extend(lints);
+        } else {
+            self.allowed_on_line.entry(line).or_default().extend(lints);
+        }
+    }
+
+    pub fn observe(&mut self, time_and_event: &TimestampedEvent) {
+        let TimestampedEvent(time, event, line) = time_and_event;
+        let (time, line) = (*time, *line);
+
+        if let Some((last, last_line)) = self.last {
+            // Going back more than 12 hours is taken as going past midnight.
+            if time < last && last - time < 12 * 60 {
+                self.warn(
+                    Lint::TimeBackwards,
+                    vec![
+                        Label::new(line, &format!("{} here", time)),
+                        Label::new(last_line, &format!("after {} here", last)),
+                    ],
+                );
+            }
+        }
+        self.last = Some((time, line));
+
+        match event {
+            Event::PeriodStart => self.start = Some((time, line)),
+            Event::Meteor(_) => self.meteors.push((time, line)),
+            Event::BreakStart => self.current_break = Some(time),
+            Event::BreakEnd => {
+                if let Some(start) = self.current_break.take() {
+                    self.breaks.push((start, time));
+                }
+            }
+            Event::Clouds(clouds) if *clouds > 20 => self.warn(
+                Lint::HeavyClouds,
+                vec![Label::new(line, &format!("{}% clouds", clouds))],
+            ),
+            Event::PeriodEnd => self.period_end(time, line),
+            Event::NewPeriod => self.reset_period(),
+            _ => {}
+        }
+    }
+
+    // A linter for another observer: the same allow directives, but nothing observed yet.
+    pub fn without_observations(&self) -> Self {
+        Self {
+            allowed: self.allowed.clone(),
+            allowed_on_line: self.allowed_on_line.clone(),
+            ..Self::default()
+        }
+    }
+
+    pub fn reset_period(&mut self) {
+        self.start = None;
+        self.meteors.clear();
+        self.breaks.clear();
+        self.current_break = None;
+    }
+
+    fn period_end(&mut self, end: Timestamp, end_line: usize) {
+        let (start, start_line) = match self.start {
+            Some(s) => s,
+            None => return,
+        };
+
+        if end - start > 120 {
+            self.warn(
+                Lint::LongPeriod,
+                vec![
+                    Label::new(start_line, &format!("period starts at {}", start)),
+                    Label::new(end_line, &format!("period ends at {}", end)),
+                ],
+            );
+        }
+
+        let meteors = self.meteors.clone();
+        for (time, line) in &meteors {
+            if *time == end {
+                self.warn(
+                    Lint::MeteorAtPeriodEnd,
+                    vec![
+                        Label::new(*line, &format!("meteor at {}", time)),
+                        Label::new(end_line, &format!("period ends at {}", end)),
+                    ],
+                );
+            }
+        }
+
+        let mut checkpoints = vec![(start, start_line)];
+        checkpoints.extend(meteors);
+        checkpoints.push((end, end_line));
+        for pair in checkpoints.windows(2) {
+            let ((from, from_line), (to, to_line)) = (pair[0], pair[1]);
+            if to - from > 12 * 60 {
+                // The time went backwards here, which is a lint of its own.
+                continue;
+            }
+            let minutes = timestamp::effective_time_minutes(from, to, &self.breaks)
+                .unwrap_or_else(|| to - from);
+            if minutes >= 60 {
+                self.warn(
+                    Lint::HourWithoutMeteors,
+                    vec![
+                        Label::new(from_line, &format!("from {}", from)),
+                        Label::new(to_line, &format!("until {}", to)),
+                    ],
+                );
+            }
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, labels: Vec<Label>) {
+        self.warnings.push(LintWarning::new(lint, labels));
+    }
+
+    // The warnings found so far, without the ones that are allowed.
+    pub fn warnings(&self) -> Vec<&LintWarning> {
+        self.warnings
+            .iter()
+            .filter(|w| !self.allowed.contains(&w.lint))
+            .filter(
+                |w| match w.line().and_then(|l| self.allowed_on_line.get(&l)) {
+                    Some(allowed) => !allowed.contains(&w.lint),
+                    None => true,
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meteor::{Meteor, MeteorAttributes, Shower};
+
+    fn at(time: u32, event: Event, line: usize) -> TimestampedEvent {
+        TimestampedEvent(Timestamp::from_shorthand_int_notation(time), event, line)
+    }
+
+    fn meteor() -> Event {
+        Event::Meteor(Meteor {
+            shower: Shower::SPORADIC,
+            magnitude: 30,
+            time: None,
+            line: None,
+            attributes: MeteorAttributes::default(),
+        })
+    }
+
+    fn codes(linter: &Linter) -> Vec<&'static str> {
+        linter.warnings().iter().map(|w| w.lint.code()).collect()
+    }
+
+    #[test]
+    fn test_lint_1() {
+        let mut linter = Linter::default();
+        linter.observe(&at(2300, Event::PeriodStart, 1));
+        linter.observe(&at(2330, Event::Clouds(30), 2));
+        linter.observe(&at(2320, meteor(), 3));
+        linter.observe(&at(15, meteor(), 4));
+        linter.observe(&at(130, meteor(), 5));
+        linter.observe(&at(130, Event::PeriodEnd, 6));
+        assert_eq!(codes(&linter), vec!["W004", "W001", "W003", "W005", "W002"]);
+        let w = linter.warnings()[4];
+        assert_eq!(w.diagnostic.lines(), vec![4, 5]);
+    }
+
+    #[test]
+    fn test_lint_2() {
+        let mut linter = Linter::default();
+        linter.observe(&at(2330, Event::PeriodStart, 1));
+        linter.observe(&at(2340, meteor(), 2));
+        linter.observe(&at(10, Event::BreakStart, 3));
+        linter.observe(&at(40, Event::BreakEnd, 4));
+        linter.observe(&at(100, meteor(), 5));
+        linter.observe(&at(110, Event::PeriodEnd, 6));
+        linter.observe(&at(110, Event::NewPeriod, 8));
+        linter.observe(&at(115, Event::PeriodStart, 9));
+        linter.observe(&at(120, Event::PeriodEnd, 10));
+        assert!(codes(&linter).is_empty());
+    }
+
+    #[test]
+    fn test_lint_allow() {
+        let mut linter = Linter::default();
+        linter.read_directive(1, "-- allow(W003)");
+        linter.read_directive(3, "clouds(30) -- allow(W004, W999)");
+        linter.read_directive(4, "clouds(30) -- a comment");
+        linter.observe(&at(2000, Event::PeriodStart, 2));
+        linter.observe(&at(2000, Event::Clouds(30), 3));
+        linter.observe(&at(2030, Event::Clouds(30), 4));
+        linter.observe(&at(2030, meteor(), 5));
+        linter.observe(&at(2259, meteor(), 6));
+        linter.observe(&at(2300, Event::PeriodEnd, 7));
+        assert_eq!(codes(&linter), vec!["W004", "W002"]);
+        assert_eq!(linter.warnings()[0].line(), Some(4));
+        assert_eq!(Lint::from_code("W002"), Some(Lint::HourWithoutMeteors));
+    }
+}
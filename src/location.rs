@@ -0,0 +1,13 @@
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: f64,
+}
+
+// The place an observation session was held, as reported to the IMO.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Site {
+    pub name: String,
+    pub location: Location,
+}
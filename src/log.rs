@@ -0,0 +1,286 @@
+use crate::builder::{BuilderWarning, Tally};
+use crate::catalog::Catalog;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::{InputOptions, Interpreter};
+use crate::lint::LintWarning;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+// A log that is written line by line while observing. Every line is validated before it
+// is accepted, and the last lines can be undone.
+pub struct LiveLog<'a> {
+    catalog: &'a Catalog,
+    options: &'a InputOptions,
+    lines: Vec<String>,
+    interpreter: Interpreter,
+}
+
+impl<'a> LiveLog<'a> {
+    pub fn new(
+        catalog: &'a Catalog,
+        options: &'a InputOptions,
+        lines: Vec<String>,
+    ) -> Result<Self, LogError> {
+        let interpreter = replay(catalog, options, &lines)?;
+        Ok(Self {
+            catalog,
+            options,
+            lines,
+            interpreter,
+        })
+    }
+
+    // Returns the warnings caused by this line.
+    pub fn enter(&mut self, line: &str) -> Result<Vec<BuilderWarning>, LogError> {
+        let warnings_before = self.interpreter.warnings().len();
+        if let Err(e) = self.interpreter.execute_one_line(line) {
+            // A failed line can leave the interpreter halfway, so start over from the accepted lines.
+            self.interpreter = replay(self.catalog, self.options, &self.lines)?;
+            return Err(LogError::Code(e));
+        }
+        self.lines.push(line.to_owned());
+        Ok(self.interpreter.warnings()[warnings_before..].to_vec())
+    }
+
+    // Removes the last line that is not blank, and returns it.
+    pub fn undo(&mut self) -> Result<Option<String>, LogError> {
+        while let Some(line) = self.lines.pop() {
+            if !line.trim().is_empty() {
+                self.interpreter = replay(self.catalog, self.options, &self.lines)?;
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn tally(&self) -> Tally {
+        self.interpreter.tally()
+    }
+
+    // The tally, preceded by the observer if one is given.
+    pub fn status(&self) -> String {
+        match self.interpreter.observer() {
+            Some(observer) => format!("{}: {}", observer, self.tally()),
+            None => self.tally().to_string(),
+        }
+    }
+
+    pub fn lints(&self) -> Vec<&LintWarning> {
+        self.interpreter.lints()
+    }
+}
+
+fn replay(
+    catalog: &Catalog,
+    options: &InputOptions,
+    lines: &[String],
+) -> Result<Interpreter, LogError> {
+    let mut interpreter = Interpreter::new(catalog).map_err(|e| LogError::Lua(e.to_string()))?;
+    options.apply(&mut interpreter);
+    for line in lines {
+        interpreter.execute_one_line(line).map_err(LogError::Code)?;
+    }
+    Ok(interpreter)
+}
+
+// Reads lines from `input` until EOF or "quit", and keeps the file at `path` in sync with
+// the accepted lines. "undo" removes the last line.
+pub fn run<R: BufRead, W: Write>(
+    path: &str,
+    catalog: &Catalog,
+    options: &InputOptions,
+    input: R,
+    mut output: W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = if Path::new(path).exists() {
+        fs::read_to_string(path)?
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    } else {
+        vec![]
+    };
+    let mut log = match LiveLog::new(catalog, options, existing) {
+        Ok(log) => log,
+        Err(LogError::Code(diagnostic)) => {
+            let source = fs::read_to_string(path)?;
+            let source: Vec<String> = source.lines().map(str::to_owned).collect();
+            writeln!(output, "{}", diagnostic.render("error", path, &source))?;
+            return Ok(());
+        }
+        Err(e) => return Err(Box::new(e)),
+    };
+    if !log.lines().is_empty() {
+        writeln!(output, "Continuing {} ({} lines).", path, log.lines().len())?;
+        writeln!(output, "{}", log.status())?;
+    }
+
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "" => continue,
+            "quit" => break,
+            "undo" => match log.undo()? {
+                Some(undone) => {
+                    write_lines(path, log.lines())?;
+                    writeln!(output, "Undone: {}", undone.trim())?;
+                }
+                None => writeln!(output, "Nothing to undo.")?,
+            },
+            _ => match log.enter(&line) {
+                Ok(warnings) => {
+                    let mut file = fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)?;
+                    writeln!(file, "{}", line)?;
+                    for warning in warnings {
+                        writeln!(output, "Warning: {}", warning)?;
+                    }
+                    let number = log.lines().len();
+                    for lint in log.lints() {
+                        if lint.diagnostic.lines().contains(&number) {
+                            writeln!(output, "{}", lint.render(path, log.lines()))?;
+                        }
+                    }
+                }
+                Err(LogError::Code(diagnostic)) => {
+                    let mut source = log.lines().to_vec();
+                    source.push(line);
+                    writeln!(
+                        output,
+                        "{}",
+                        diagnostic.render("not accepted", path, &source)
+                    )?;
+                    continue;
+                }
+                Err(e) => return Err(Box::new(e)),
+            },
+        }
+        writeln!(output, "{}", log.status())?;
+    }
+    Ok(())
+}
+
+fn write_lines(path: &str, lines: &[String]) -> std::io::Result<()> {
+    let mut contents = lines.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    fs::write(path, contents)
+}
+
+#[derive(Debug, Clone)]
+pub enum LogError {
+    Lua(String),
+    Code(Diagnostic),
+}
+
+impl std::fmt::Display for LogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Lua(e) => write!(f, "Could not initialize Lua context. Error: {}", e),
+            Self::Code(diagnostic) => write!(f, "{}", diagnostic),
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meteor::Shower;
+    use crate::sqm::SqmConversion;
+
+    fn lines(code: &str) -> Vec<String> {
+        code.lines().map(|l| l.trim().to_owned()).collect()
+    }
+
+    #[test]
+    fn test_live_log_1() {
+        let catalog = Catalog::bundled();
+        let options = InputOptions::default();
+        let mut log = LiveLog::new(
+            &catalog,
+            &options,
+            lines(
+                "2300
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(PER, SPO)",
+            ),
+        )
+        .unwrap();
+        log.enter("clouds(0)").unwrap();
+        log.enter("per(3) << 2310").unwrap();
+        log.enter("per(2)").unwrap();
+        assert_eq!(log.tally().counts[0], (Shower(*b"PER"), 2));
+
+        match log.enter("kcg(2)") {
+            Err(LogError::Code(diagnostic)) => assert_eq!(diagnostic.lines(), vec![4, 8]),
+            _ => panic!("enter does not return an error"),
+        }
+        assert_eq!(log.lines().len(), 7);
+
+        assert_eq!(log.undo().unwrap(), Some("per(2)".to_owned()));
+        assert_eq!(log.tally().counts[0], (Shower(*b"PER"), 1));
+        assert_eq!(log.tally().teff, 10_f64 / 60_f64);
+    }
+
+    #[test]
+    fn test_live_log_2() {
+        let catalog = Catalog::bundled();
+        let options = InputOptions::default();
+        let mut log = LiveLog::new(&catalog, &options, lines("2300\n\n")).unwrap();
+        assert_eq!(log.undo().unwrap(), Some("2300".to_owned()));
+        assert_eq!(log.undo().unwrap(), None);
+
+        let warnings = log.enter("2300").and_then(|_| {
+            log.enter("date(\"12 Aug 2019\")")?;
+            log.enter("showers(GEM)")
+        });
+        assert_eq!(warnings.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_live_log_3() {
+        let catalog = Catalog::bundled();
+        match LiveLog::new(&catalog, &InputOptions::default(), lines("period_start")) {
+            Err(LogError::Code(diagnostic)) => assert_eq!(diagnostic.lines(), vec![1]),
+            _ => panic!("new does not return an error"),
+        }
+    }
+
+    #[test]
+    fn test_live_log_options() {
+        let catalog = Catalog::bundled();
+        let options = InputOptions {
+            sqm_conversion: SqmConversion { a: 7.0, b: 4.316 },
+            ..InputOptions::default()
+        };
+        let mut log = LiveLog::new(
+            &catalog,
+            &options,
+            lines(
+                "2300
+                 period_start
+                 date(\"12 Aug 2019\")
+                 showers(SPO)",
+            ),
+        )
+        .unwrap();
+        log.enter("sqm(21.35)").unwrap();
+        let lm = log.tally().limiting_magnitude.unwrap();
+        assert!((lm - 5.38).abs() < 0.01);
+        // The options are kept when the log is replayed.
+        log.enter("clouds(0)").unwrap();
+        log.undo().unwrap();
+        assert_eq!(log.tally().limiting_magnitude, Some(lm));
+    }
+}
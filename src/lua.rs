@@ -1,10 +1,13 @@
 use crate::areas::Area;
+use crate::catalog::Catalog;
 use crate::field::Field;
-use crate::meteor::{Meteor, Shower};
+use crate::location::{Location, Site};
+use crate::meteor::{Meteor, MeteorAttributes, Shower};
 use crate::session::Event;
 use crate::stars::STARS;
+use crate::timestamp::Date;
 use rlua;
-use rlua::{Function, Lua, UserData};
+use rlua::{Function, Lua, Table, UserData, Value};
 
 impl UserData for Area {}
 impl UserData for Meteor {}
@@ -16,60 +19,128 @@ struct Count(usize, Area);
 
 impl UserData for Count {}
 
-macro_rules! register_shower {
-    ($shower_enum:ident, $lua_ctx:ident, $globals:ident) => {
-        let shower_fn = $lua_ctx.create_function(|_, mag: f64| {
-            let meteor = Meteor {
-                shower: Shower::$shower_enum,
-                magnitude: (mag * 10.0) as i32,
-            };
-            if meteor.magnitude % 5 == 0 {
-                Ok(Event::Meteor(meteor))
-            } else {
-                Err(runtime_error("Invalid magnitude for given meteor"))
-            }
-        })?;
-        $globals.set(Shower::$shower_enum.to_imo_code().to_lowercase(), shower_fn)?;
-        $globals.set(Shower::$shower_enum.to_imo_code(), Shower::$shower_enum)?;
-    };
-}
-
 fn runtime_error(desc: &str) -> rlua::Error {
     rlua::Error::RuntimeError(String::from(desc))
 }
 
+fn location(latitude: f64, longitude: f64, elevation: f64) -> Result<Location, rlua::Error> {
+    if !(-90.0..=90.0).contains(&latitude) {
+        Err(runtime_error("Latitude must be between -90 and 90 degrees"))
+    } else if !(-180.0..=180.0).contains(&longitude) {
+        Err(runtime_error(
+            "Longitude must be between -180 and 180 degrees",
+        ))
+    } else {
+        Ok(Location {
+            latitude,
+            longitude,
+            elevation,
+        })
+    }
+}
+
+// Reads the table with attributes that can follow the magnitude of a meteor, like
+// `per(-2, {train=5, color="green", note="flare"})`.
+fn meteor_attributes(table: Option<Table>) -> Result<MeteorAttributes, rlua::Error> {
+    let mut attributes = MeteorAttributes::default();
+    let table = match table {
+        Some(t) => t,
+        None => return Ok(attributes),
+    };
+    for pair in table.pairs::<String, Value>() {
+        let (key, value) = pair?;
+        match (key.as_str(), value) {
+            ("train", Value::Integer(seconds)) if seconds >= 0 => {
+                attributes.train = Some(seconds as f64)
+            }
+            ("train", Value::Number(seconds)) if seconds >= 0_f64 => {
+                attributes.train = Some(seconds)
+            }
+            ("color", Value::String(color)) => attributes.color = Some(color.to_str()?.to_owned()),
+            ("note", Value::String(note)) => attributes.note = Some(note.to_str()?.to_owned()),
+            ("train", _) => {
+                return Err(runtime_error(
+                    "The train must be a number of seconds, not negative",
+                ))
+            }
+            ("color", _) | ("note", _) => {
+                return Err(runtime_error(&format!("The {} must be a string", key)))
+            }
+            _ => return Err(runtime_error(&format!("Unknown meteor attribute: {}", key))),
+        }
+    }
+    Ok(attributes)
+}
+
+// The names that the input already uses, so the lowercase code of a shower in the catalog
+// cannot be one of them: the Lua keywords and the globals set below, besides `area1` and so on.
+pub const RESERVED_NAMES: &[&str] = &[
+    "and",
+    "break",
+    "do",
+    "else",
+    "elseif",
+    "end",
+    "false",
+    "for",
+    "function",
+    "goto",
+    "if",
+    "in",
+    "local",
+    "nil",
+    "not",
+    "or",
+    "repeat",
+    "return",
+    "then",
+    "true",
+    "until",
+    "while",
+    "areas",
+    "break_end",
+    "break_start",
+    "clouds",
+    "date",
+    "fieldC",
+    "fieldN",
+    "lm",
+    "location",
+    "new_period",
+    "observer",
+    "period_end",
+    "period_start",
+    "showers",
+    "site",
+    "sqm",
+];
+
 #[allow(clippy::type_complexity)]
-pub fn new_lua() -> Result<Lua, rlua::Error> {
+pub fn new_lua(catalog: &Catalog) -> Result<Lua, rlua::Error> {
     let l = Lua::new();
     l.context(|lua_ctx| -> Result<(), rlua::Error> {
         let globals = lua_ctx.globals();
 
-        register_shower!(Quadrantids, lua_ctx, globals);
-        register_shower!(Lyrids, lua_ctx, globals);
-        register_shower!(EtaAquarids, lua_ctx, globals);
-        register_shower!(JuneBootids, lua_ctx, globals);
-        register_shower!(DeltaAquariids, lua_ctx, globals);
-        register_shower!(AlphaCapricornids, lua_ctx, globals);
-        register_shower!(Perseids, lua_ctx, globals);
-        register_shower!(KappaCygnids, lua_ctx, globals);
-        register_shower!(AlphaAurigids, lua_ctx, globals);
-        register_shower!(SeptemberEpsilonPerseids, lua_ctx, globals);
-        register_shower!(OctoberCameloparalids, lua_ctx, globals);
-        register_shower!(Draconids, lua_ctx, globals);
-        register_shower!(EpsilonGeminids, lua_ctx, globals);
-        register_shower!(Orionids, lua_ctx, globals);
-        register_shower!(SouthernTaurids, lua_ctx, globals);
-        register_shower!(NorthernTaurids, lua_ctx, globals);
-        register_shower!(Leonids, lua_ctx, globals);
-        register_shower!(DecemberAlphaDraconids, lua_ctx, globals);
-        register_shower!(Monocerotids, lua_ctx, globals);
-        register_shower!(SigmaHydrids, lua_ctx, globals);
-        register_shower!(Geminids, lua_ctx, globals);
-        register_shower!(DecemberLeoMinorids, lua_ctx, globals);
-        register_shower!(ComaBerenicids, lua_ctx, globals);
-        register_shower!(Ursids, lua_ctx, globals);
-        register_shower!(Antihelion, lua_ctx, globals);
-        register_shower!(Sporadic, lua_ctx, globals);
+        for info in catalog.showers() {
+            let shower = info.shower;
+            let shower_fn =
+                lua_ctx.create_function(move |_, (mag, attributes): (f64, Option<Table>)| {
+                    let meteor = Meteor {
+                        shower,
+                        magnitude: (mag * 10.0) as i32,
+                        time: None,
+                        line: None,
+                        attributes: meteor_attributes(attributes)?,
+                    };
+                    if meteor.magnitude % 5 == 0 {
+                        Ok(Event::Meteor(meteor))
+                    } else {
+                        Err(runtime_error("Invalid magnitude for given meteor"))
+                    }
+                })?;
+            globals.set(shower.to_imo_code().to_lowercase(), shower_fn)?;
+            globals.set(shower.to_imo_code(), shower)?;
+        }
 
         globals.set("break_start", Event::BreakStart)?;
         globals.set("break_end", Event::BreakEnd)?;
@@ -92,6 +163,26 @@ pub fn new_lua() -> Result<Lua, rlua::Error> {
         })?;
         globals.set("clouds", cloud_fn)?;
 
+        let lm_fn = lua_ctx.create_function(|_, lm: f64| {
+            if !(1.0..=8.0).contains(&lm) {
+                Err(runtime_error("Limiting magnitude must be between 1 and 8"))
+            } else {
+                Ok(Event::LimitingMagnitude(lm))
+            }
+        })?;
+        globals.set("lm", lm_fn)?;
+
+        let sqm_fn = lua_ctx.create_function(|_, reading: f64| {
+            if !(10.0..=25.0).contains(&reading) {
+                Err(runtime_error(
+                    "SQM reading must be between 10 and 25 mag/arcsec²",
+                ))
+            } else {
+                Ok(Event::Sqm(reading))
+            }
+        })?;
+        globals.set("sqm", sqm_fn)?;
+
         let area_fn = lua_ctx.create_function(
             |_,
              counts: (
@@ -133,6 +224,35 @@ pub fn new_lua() -> Result<Lua, rlua::Error> {
             })?;
         globals.set("fieldN", field_name_fn)?;
 
+        let location_fn =
+            lua_ctx.create_function(|_, (latitude, longitude, elevation): (f64, f64, f64)| {
+                Ok(Event::Location(location(latitude, longitude, elevation)?))
+            })?;
+        globals.set("location", location_fn)?;
+
+        // The code also goes into the names of the output files.
+        let observer_fn = lua_ctx.create_function(|_, code: String| {
+            let code = code.trim();
+            if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+                Err(runtime_error(
+                    "The observer code must consist of letters and digits, like DANTH",
+                ))
+            } else {
+                Ok(Event::Observer(code.to_owned()))
+            }
+        })?;
+        globals.set("observer", observer_fn)?;
+
+        let site_fn = lua_ctx.create_function(
+            |_, (name, latitude, longitude, elevation): (String, f64, f64, f64)| {
+                Ok(Event::Site(Site {
+                    name,
+                    location: location(latitude, longitude, elevation)?,
+                }))
+            },
+        )?;
+        globals.set("site", site_fn)?;
+
         let showers_fn = lua_ctx.create_function(
             |_,
              showers: (
@@ -163,7 +283,12 @@ pub fn new_lua() -> Result<Lua, rlua::Error> {
         )?;
         globals.set("showers", showers_fn)?;
 
-        let date_fn = lua_ctx.create_function(|_, date: String| Ok(Event::PeriodDate(date)))?;
+        let date_fn = lua_ctx.create_function(|_, date: String| match Date::parse(&date) {
+            Some(d) => Ok(Event::PeriodDate(d)),
+            None => Err(runtime_error(
+                "Invalid date. Use the notation \"11 Aug 2019\" or \"Aug 11 2019\".",
+            )),
+        })?;
         globals.set("date", date_fn)?;
 
         Ok(())
@@ -186,13 +311,16 @@ mod tests {
 
     #[test]
     fn test_lua_1() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
         let event = run_code("per(3.5)", &l).unwrap();
         assert_eq!(
             event,
             Event::Meteor(Meteor {
-                shower: Shower::Perseids,
+                shower: Shower(*b"PER"),
                 magnitude: 35,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
             })
         );
 
@@ -200,15 +328,18 @@ mod tests {
         assert_eq!(
             event,
             Event::Meteor(Meteor {
-                shower: Shower::Sporadic,
-                magnitude: -20
+                shower: Shower(*b"SPO"),
+                magnitude: -20,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
             })
         );
     }
 
     #[test]
     fn test_lua_2() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         assert_eq!(run_code("break_start", &l).unwrap(), Event::BreakStart);
         assert_eq!(run_code("break_end", &l).unwrap(), Event::BreakEnd);
@@ -219,7 +350,7 @@ mod tests {
 
     #[test]
     fn test_lua_3() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         assert_eq!(
             run_code("fieldC(336, 52.3)", &l).unwrap(),
@@ -232,21 +363,21 @@ mod tests {
 
     #[test]
     fn test_lua_4() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         assert_eq!(run_code("clouds(5)", &l).unwrap(), Event::Clouds(5));
     }
 
     #[test]
     fn test_lua_5() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         assert!(run_code("clouds(101)", &l).is_err());
     }
 
     #[test]
     fn test_lua_6() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         let ac = run_code("areas(area14(10), area7(11), area6(7))", &l).unwrap();
         assert_eq!(
@@ -257,29 +388,29 @@ mod tests {
 
     #[test]
     fn test_lua_7() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         let showers = run_code("showers(PER, ANT, KCG, SPO)", &l).unwrap();
         assert_eq!(
             showers,
             Event::Showers(vec![
-                Shower::Perseids,
-                Shower::Antihelion,
-                Shower::KappaCygnids,
-                Shower::Sporadic
+                Shower(*b"PER"),
+                Shower(*b"ANT"),
+                Shower(*b"KCG"),
+                Shower(*b"SPO")
             ])
         );
     }
 
     #[test]
     fn test_lua_8() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
         assert!(run_code("per(3.7)", &l).is_err());
     }
 
     #[test]
     fn test_lua_9() {
-        let l = new_lua().unwrap();
+        let l = new_lua(&Catalog::bundled()).unwrap();
 
         assert_eq!(
             run_code("fieldN(\"Deneb\")", &l).unwrap(),
@@ -289,4 +420,133 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_lua_10() {
+        let l = new_lua(&Catalog::bundled()).unwrap();
+
+        assert_eq!(
+            run_code("location(51.05, 3.72, 10)", &l).unwrap(),
+            Event::Location(Location {
+                latitude: 51.05,
+                longitude: 3.72,
+                elevation: 10.0
+            })
+        );
+        assert!(run_code("location(91, 3.72, 10)", &l).is_err());
+        assert!(run_code("location(51.05, -181, 10)", &l).is_err());
+    }
+
+    #[test]
+    fn test_lua_11() {
+        let mut catalog = Catalog::bundled();
+        catalog.merge(
+            Catalog::parse(
+                "CODE;NAME;START;END;PEAK;RA;DEC;DRA;DDEC;V;R
+NDA;Northern delta-Aquariids;Jul 15;Aug 25;139;335;-5;;;42;3.4",
+            )
+            .unwrap(),
+        );
+        let l = new_lua(&catalog).unwrap();
+
+        assert_eq!(
+            run_code("nda(2)", &l).unwrap(),
+            Event::Meteor(Meteor {
+                shower: Shower(*b"NDA"),
+                magnitude: 20,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
+            })
+        );
+        assert_eq!(
+            run_code("showers(NDA, SPO)", &l).unwrap(),
+            Event::Showers(vec![Shower(*b"NDA"), Shower::SPORADIC])
+        );
+        assert!(run_code("nda(2)", &new_lua(&Catalog::bundled()).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_lua_12() {
+        let l = new_lua(&Catalog::bundled()).unwrap();
+
+        let date = Event::PeriodDate(Date {
+            year: 2019,
+            month: 8,
+            day: 11,
+        });
+        assert_eq!(run_code("date(\"11 Aug 2019\")", &l).unwrap(), date);
+        assert_eq!(run_code("date(\"Aug 11 2019\")", &l).unwrap(), date);
+        assert!(run_code("date(\"2019-08-11\")", &l).is_err());
+    }
+
+    #[test]
+    fn test_lua_13() {
+        let l = new_lua(&Catalog::bundled()).unwrap();
+
+        assert_eq!(
+            run_code("observer(\"DANTH\")", &l).unwrap(),
+            Event::Observer("DANTH".to_owned())
+        );
+        assert!(run_code("observer(\" \")", &l).is_err());
+        assert!(run_code("observer(\"../x\")", &l).is_err());
+        assert!(run_code("observer(\"A/B\")", &l).is_err());
+        assert_eq!(
+            run_code("site(\"Gent\", 51.05, 3.72, 10)", &l).unwrap(),
+            Event::Site(Site {
+                name: "Gent".to_owned(),
+                location: Location {
+                    latitude: 51.05,
+                    longitude: 3.72,
+                    elevation: 10.0
+                }
+            })
+        );
+        assert!(run_code("site(\"Gent\", 51.05, 200, 10)", &l).is_err());
+    }
+
+    #[test]
+    fn test_lua_14() {
+        let l = new_lua(&Catalog::bundled()).unwrap();
+
+        match run_code("per(-2, {train=5, color=\"green\", note=\"flare\"})", &l).unwrap() {
+            Event::Meteor(meteor) => {
+                assert_eq!(meteor.magnitude, -20);
+                assert_eq!(
+                    meteor.attributes,
+                    MeteorAttributes {
+                        train: Some(5.0),
+                        color: Some("green".to_owned()),
+                        note: Some("flare".to_owned()),
+                    }
+                );
+            }
+            _ => panic!("per does not return a meteor"),
+        }
+        match run_code("spo(1, {train=1.5})", &l).unwrap() {
+            Event::Meteor(meteor) => assert_eq!(meteor.attributes.train, Some(1.5)),
+            _ => panic!("spo does not return a meteor"),
+        }
+        assert!(run_code("spo(1, {train=-1})", &l).is_err());
+        assert!(run_code("spo(1, {color=3})", &l).is_err());
+        assert!(run_code("spo(1, {speed=3})", &l).is_err());
+    }
+
+    #[test]
+    fn test_lua_15() {
+        let l = new_lua(&Catalog::bundled()).unwrap();
+
+        match run_code("lm(6.2)", &l).unwrap() {
+            Event::LimitingMagnitude(lm) => assert_eq!(lm, 6.2),
+            _ => panic!("lm does not return a limiting magnitude"),
+        }
+        assert!(run_code("lm(0.5)", &l).is_err());
+        assert!(run_code("lm(8.5)", &l).is_err());
+
+        match run_code("sqm(21.35)", &l).unwrap() {
+            Event::Sqm(reading) => assert_eq!(reading, 21.35),
+            _ => panic!("sqm does not return a reading"),
+        }
+        assert!(run_code("sqm(30)", &l).is_err());
+    }
 }
@@ -1,18 +1,33 @@
 #![feature(try_trait)]
 
 mod areas;
+mod astro;
 mod builder;
+mod catalog;
+mod diagnostic;
 mod distribution;
 mod factors;
 mod field;
+mod fireball;
+mod import;
 mod interpreter;
+mod lint;
+mod location;
+mod log;
 mod lua;
+mod merge;
 mod meteor;
+mod population;
+mod rebin;
 mod session;
+mod sqm;
 mod stars;
+mod stats;
 mod timestamp;
+mod zhr;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
@@ -21,6 +36,7 @@ fn main() {
     let matches = App::new("Meteoraid")
         .version("0.1.0")
         .about("Processes visual meteor observations")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("INPUT")
                 .help("Specified the input code file to process")
@@ -48,107 +64,883 @@ fn main() {
                 .long("force")
                 .help("Overwrite output files if they already exist."),
         )
+        .arg(
+            Arg::with_name("output-extended")
+                .short("e")
+                .long("output-extended")
+                .value_name("PATH")
+                .help("Path to store the count CSV extended with the solar longitudes.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-meteors")
+                .short("m")
+                .long("output-meteors")
+                .value_name("PATH")
+                .help("Path to store a CSV with every meteor and the time it was seen.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-zhr")
+                .short("z")
+                .long("output-zhr")
+                .value_name("PATH")
+                .help("Path to store the CSV with the ZHR per period and shower.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-fireballs")
+                .long("output-fireballs")
+                .value_name("PATH")
+                .help("Path to store the meteors of magnitude -3 or brighter, for the IMO fireball report.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("area-centres")
+                .long("area-centres")
+                .value_name("PATH")
+                .help("CSV with the centres of the IMO star-count areas (AREA;RA;DEC), instead of the bundled ones.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("min-area-altitude")
+                .long("min-area-altitude")
+                .value_name("DEGREES")
+                .help("Minimum altitude of the centre of a counted area.")
+                .default_value("30")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("extinction")
+                .long("extinction")
+                .value_name("K")
+                .help("Extinction coefficient to correct the Lm of counted areas for the altitude of their centres.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("explain-lm")
+                .long("explain-lm")
+                .help("Shows the limiting magnitude of every area of each `areas`, and which ones were averaged."),
+        )
+        .arg(
+            Arg::with_name("output-lm")
+                .long("output-lm")
+                .value_name("PATH")
+                .help("Path to store the limiting magnitude estimates and how they were made.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-magnitudes")
+                .long("output-magnitudes")
+                .value_name("PATH")
+                .help("Path to store the magnitude statistics per period and shower.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-trains")
+                .long("output-trains")
+                .value_name("PATH")
+                .help("Path to store a CSV with the percentage of meteors with trains, per shower and magnitude.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output-summary")
+                .long("output-summary")
+                .value_name("PATH")
+                .help("Path to store a CSV with the totals per observer and for all observers.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("population-index")
+                .short("r")
+                .long("population-index")
+                .value_name("R")
+                .help(
+                    "Population index used for the ZHR computation, instead of the catalog value.",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("fit-population-index")
+                .long("fit-r")
+                .help("Use the population index fitted on the magnitude distribution for the ZHR computation, when there are enough meteors."),
+        )
+        .arg(
+            Arg::with_name("output-population-index")
+                .long("output-r")
+                .value_name("PATH")
+                .help("Path to store the population index per shower, fitted on the magnitude distribution.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("radiant-alt")
+                .long("radiant-alt")
+                .value_name("DEGREES")
+                .help(
+                    "Radiant altitude used for the ZHR computation of periods without a location.",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sqm-formula")
+                .long("sqm-formula")
+                .value_name("A,B")
+                .help("Constants of the conversion of sqm readings: Lm = A - 5 log10(10^(B - SQM/5) + 1).")
+                .default_value("7.93,4.316")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rebin")
+                .long("rebin")
+                .value_name("MINUTES")
+                .help("Split the periods at multiples of this many minutes after 0h UT.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rebin-sol")
+                .long("rebin-sol")
+                .value_name("DEGREES")
+                .help("Split the periods at multiples of this step in solar longitude.")
+                .takes_value(true)
+                .conflicts_with("rebin"),
+        )
+        .arg(
+            Arg::with_name("merge")
+                .long("merge")
+                .value_name("MINUTES")
+                .help("Merge consecutive periods into periods of at most this many minutes.")
+                .takes_value(true)
+                .conflicts_with_all(&["rebin", "rebin-sol"]),
+        )
+        .arg(
+            Arg::with_name("merge-field-distance")
+                .long("merge-field-distance")
+                .value_name("DEGREES")
+                .help("Largest distance between the fields of periods that are merged.")
+                .default_value("10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("Treat warnings about the input as errors."),
+        )
+        .arg(
+            Arg::with_name("showers")
+                .long("showers")
+                .value_name("PATH")
+                .help("Shower catalog that adds to or overrides the bundled one.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("Only check the input file, and report all errors and warnings in it.")
+                .conflicts_with("from-csv"),
+        )
+        .arg(
+            Arg::with_name("from-csv")
+                .long("from-csv")
+                .value_name("DISTR_PATH")
+                .help("Read INPUT as a count CSV, with the distribution CSV at DISTR_PATH, instead of as an input code file.")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("log")
+                .about("Logs observations line by line, validating every line as it is typed")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Input code file to append to (created if it doesn't exist)")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("areas")
+                .about("Lists the altitude of the star-count areas")
+                .arg(
+                    Arg::with_name("LAT")
+                        .help("Latitude in degrees")
+                        .required(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::with_name("LON")
+                        .help("Longitude in degrees, positive to the east")
+                        .required(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(
+                    Arg::with_name("DATE")
+                        .help("Date, like \"12 Aug 2019\"")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("TIME")
+                        .help("Time in UT, like 2330")
+                        .required(true),
+                ),
+        )
         .get_matches();
 
     let force_overwrite = matches.is_present("force");
     let output_count = matches.value_of("output-count");
     let output_distr = matches.value_of("output-distr");
+    let output_extended = matches.value_of("output-extended");
+    let output_meteors = matches.value_of("output-meteors");
+    let output_zhr = matches.value_of("output-zhr");
+    let output_summary = matches.value_of("output-summary");
+    let output_trains = matches.value_of("output-trains");
+    let output_magnitudes = matches.value_of("output-magnitudes");
+    let output_lm = matches.value_of("output-lm");
+    let output_fireballs = matches.value_of("output-fireballs");
+    let output_population_index = matches.value_of("output-population-index");
+    let fit_population_index = matches.is_present("fit-population-index");
 
-    let input_file = matches.value_of("INPUT").unwrap();
+    let zhr_parameters = if output_zhr.is_some() {
+        let population_index = match matches.value_of("population-index").map(str::parse) {
+            Some(Ok(r)) => Some(r),
+            Some(Err(e)) => {
+                eprintln!("Invalid population index: {}", e);
+                return;
+            }
+            None => None,
+        };
+        let radiant_altitude = match matches.value_of("radiant-alt").map(str::parse) {
+            Some(Ok(h)) => Some(h),
+            Some(Err(e)) => {
+                eprintln!("Invalid radiant altitude: {}", e);
+                return;
+            }
+            None => None,
+        };
+        Some(zhr::ZhrParameters {
+            population_index,
+            population_indices: HashMap::new(),
+            radiant_altitude,
+        })
+    } else {
+        None
+    };
 
-    let mut intrprtr = match interpreter::Interpreter::new() {
-        Ok(x) => x,
+    let mut catalog = catalog::Catalog::bundled();
+    if let Some(path) = matches.value_of("showers") {
+        let user_catalog = match fs::read_to_string(path) {
+            Ok(data) => catalog::Catalog::parse(&data),
+            Err(e) => {
+                eprintln!("Error when reading shower catalog: {}", e);
+                return;
+            }
+        };
+        match user_catalog {
+            Ok(c) => catalog.merge(c),
+            Err(e) => {
+                eprintln!("Invalid shower catalog. {}", e);
+                return;
+            }
+        }
+    }
+
+    let min_area_altitude: f64 = match matches.value_of("min-area-altitude").unwrap().parse() {
+        Ok(h) => h,
         Err(e) => {
-            eprintln!("Could not initialize Lua context. Error: {}", e);
+            eprintln!("Invalid minimum area altitude: {}", e);
             return;
         }
     };
-
-    match fs::File::open(input_file) {
-        Ok(file) => {
-            let reader = BufReader::new(&file);
-            for (num, line) in reader.lines().enumerate() {
-                let line_text = match line {
-                    Ok(x) => x,
-                    Err(e) => {
-                        eprintln!("Error when reading line: {}", e);
-                        return;
-                    }
-                };
-                match intrprtr.execute_one_line(&line_text) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        eprintln!("Error while executing code, line {}:\n{}", num + 1, e);
-                        return;
-                    }
-                };
+    let area_centres = match matches.value_of("area-centres") {
+        Some(path) => {
+            let centres = match fs::read_to_string(path) {
+                Ok(data) => areas::AreaCentres::parse(&data).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match centres {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Invalid area centres. {}", e);
+                    return;
+                }
             }
         }
-        Err(e) => eprintln!("Error when reading file: {}", e),
+        None => areas::AreaCentres::bundled(),
     };
 
-    let session = match intrprtr.get_session() {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Error while executing code, EOF:\n{}", e);
+    let sqm_conversion = match matches.value_of("sqm-formula").unwrap().parse() {
+        Ok(c) => c,
+        Err(_) => {
+            eprintln!("Invalid --sqm-formula, give the constants as A,B.");
             return;
         }
     };
 
-    let (count_csv, distr_csv) = match session.get_csvs() {
-        Ok(csvs) => csvs,
-        Err(e) => {
-            eprintln!("Error while generating CSVs: {:?}", e);
+    // Without centres there is nothing to check or correct.
+    let area_check = if area_centres.is_empty() {
+        None
+    } else {
+        Some((area_centres, min_area_altitude))
+    };
+    let extinction = match matches.value_of("extinction").map(str::parse::<f64>) {
+        Some(Ok(k)) if k >= 0_f64 && area_check.is_some() => Some(k),
+        Some(Ok(k)) if k >= 0_f64 => {
+            eprintln!("--extinction needs the centres of the areas, given with --area-centres.");
+            return;
+        }
+        Some(_) => {
+            eprintln!("Invalid extinction coefficient.");
             return;
         }
+        None => None,
+    };
+    let options = interpreter::InputOptions {
+        strict: matches.is_present("strict"),
+        sqm_conversion,
+        area_check,
+        extinction,
+    };
+
+    if let Some(areas_matches) = matches.subcommand_matches("areas") {
+        match &options.area_check {
+            Some((centres, min_altitude)) => {
+                print_area_altitudes(areas_matches, centres, *min_altitude)
+            }
+            None => {
+                eprintln!("The centres of the areas are not known, give them with --area-centres.")
+            }
+        }
+        return;
+    }
+
+    if let Some(log_matches) = matches.subcommand_matches("log") {
+        let path = log_matches.value_of("FILE").unwrap();
+        let stdin = std::io::stdin();
+        if let Err(e) = log::run(path, &catalog, &options, stdin.lock(), std::io::stdout()) {
+            eprintln!("Error while logging: {}", e);
+        }
+        return;
+    }
+
+    let binning = match (matches.value_of("rebin"), matches.value_of("rebin-sol")) {
+        (Some(minutes), _) => match minutes.parse() {
+            Ok(n) if n > 0 => Some(rebin::Binning::Minutes(n)),
+            _ => {
+                eprintln!("Invalid number of minutes to rebin: {}", minutes);
+                return;
+            }
+        },
+        (_, Some(step)) => match step.parse() {
+            Ok(s) if s > 0_f64 => Some(rebin::Binning::SolarLongitude(s)),
+            _ => {
+                eprintln!("Invalid solar longitude step to rebin: {}", step);
+                return;
+            }
+        },
+        _ => None,
+    };
+
+    let merge = match matches.value_of("merge") {
+        Some(minutes) => match (
+            minutes.parse(),
+            matches.value_of("merge-field-distance").unwrap().parse(),
+        ) {
+            (Ok(n), Ok(d)) if n > 0 && d >= 0_f64 => Some((n, d)),
+            _ => {
+                eprintln!("Invalid --merge or --merge-field-distance value.");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let input_file = matches.value_of("INPUT").unwrap();
+    if matches.is_present("check") {
+        if !check_input(input_file, &catalog, &options) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let sessions = match matches.value_of("from-csv") {
+        Some(distr_file) => session_from_csvs(input_file, distr_file).map(|s| vec![s]),
+        None => session_from_input(
+            input_file,
+            &catalog,
+            &options,
+            matches.is_present("explain-lm"),
+        ),
+    };
+    let sessions = match sessions {
+        Some(s) => s,
+        None => return,
     };
 
-    if let Some(output_count_path) = output_count {
-        if force_overwrite || !Path::new(&output_count_path).exists() {
-            match fs::write(&output_count_path, &count_csv) {
-                Ok(_) => println!("Count CSV written to {}.", &output_count_path),
+    let mut processed = vec![];
+    for mut session in sessions {
+        if let Some(binning) = binning {
+            session = match session.rebin(binning) {
+                Ok(s) => s,
                 Err(e) => {
-                    eprintln!("Error while writing count CSV: {}", e);
+                    eprintln!("Error while rebinning. {}", e);
                     return;
                 }
+            };
+        }
+
+        if let Some((max_minutes, max_field_distance)) = merge {
+            let (merged, refused) = session.merge_periods(max_minutes, max_field_distance);
+            for (period, e) in refused {
+                eprintln!(
+                    "Note: period {} is not merged with the one before it. {}",
+                    period, e
+                );
             }
-        } else {
-            println!(
-                "{} already exists and -f flag not set, outputting count CSV to stdout:",
-                output_count_path
+            session = merged;
+        }
+
+        for warning in horizon_warnings(&session, None, &catalog) {
+            eprintln!("Warning: {}", warning);
+        }
+        processed.push(session);
+    }
+
+    // With several observers, every observer gets their own files, named after the
+    // observer code.
+    let multiple = processed.len() > 1;
+    for session in &processed {
+        let observer = match &session.header.observer {
+            Some(observer) if multiple => Some(observer.as_str()),
+            _ => None,
+        };
+        let path = |path: Option<&str>| path.map(|p| observer_path(p, observer));
+        let title = |title: &str| match observer {
+            Some(observer) => format!("{} of {}", title, observer),
+            None => title.to_owned(),
+        };
+
+        let (count_csv, distr_csv) = match session.get_csvs() {
+            Ok(csvs) => csvs,
+            Err(e) => {
+                eprintln!("Error while generating CSVs: {:?}", e);
+                return;
+            }
+        };
+
+        if !write_output(
+            &title("Count CSV"),
+            "count CSV",
+            path(output_count).as_deref(),
+            &count_csv,
+            force_overwrite,
+        ) {
+            return;
+        }
+        if !write_output(
+            &title("Distribution CSV"),
+            "distribution CSV",
+            path(output_distr).as_deref(),
+            &distr_csv,
+            force_overwrite,
+        ) {
+            return;
+        }
+        if output_extended.is_some() {
+            let extended_csv = session.get_extended_count_csv();
+            if !write_output(
+                &title("Extended count CSV"),
+                "extended count CSV",
+                path(output_extended).as_deref(),
+                &extended_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+        if output_meteors.is_some() {
+            let meteor_csv = session.get_meteor_csv();
+            if !write_output(
+                &title("Meteor CSV"),
+                "meteor CSV",
+                path(output_meteors).as_deref(),
+                &meteor_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+        let fireballs = session.fireballs().len();
+        if output_fireballs.is_some() {
+            let fireball_csv = session.get_fireball_csv();
+            if !write_output(
+                &title("Fireball CSV"),
+                "fireball CSV",
+                path(output_fireballs).as_deref(),
+                &fireball_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        } else if fireballs > 0 {
+            eprintln!(
+                "Note: {} fireball(s) of magnitude -3 or brighter. Use --output-fireballs to report them.",
+                fireballs
             );
-            println!("--------------------------------------------");
-            println!("{}", count_csv);
-            println!("--------------------------------------------");
         }
-    } else {
-        println!("Count CSV:");
-        println!("--------------------------------------------");
-        println!("{}", count_csv);
-        println!("--------------------------------------------");
+        if output_lm.is_some() {
+            let lm_csv = session.get_lm_csv();
+            if !write_output(
+                &title("Lm CSV"),
+                "Lm CSV",
+                path(output_lm).as_deref(),
+                &lm_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+        if output_magnitudes.is_some() {
+            let magnitude_csv = session.get_magnitude_csv();
+            if !write_output(
+                &title("Magnitude CSV"),
+                "magnitude CSV",
+                path(output_magnitudes).as_deref(),
+                &magnitude_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+        if output_trains.is_some() {
+            let train_csv = session.get_train_csv();
+            if !write_output(
+                &title("Train CSV"),
+                "train CSV",
+                path(output_trains).as_deref(),
+                &train_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+        if let Some(parameters) = &zhr_parameters {
+            let mut parameters = parameters.clone();
+            if fit_population_index {
+                parameters.population_indices = session.population_indices();
+            }
+            let zhr_csv = session.get_zhr_csv(&parameters, &catalog);
+            if !write_output(
+                &title("ZHR CSV"),
+                "ZHR CSV",
+                path(output_zhr).as_deref(),
+                &zhr_csv,
+                force_overwrite,
+            ) {
+                return;
+            }
+        }
+    }
+    if output_population_index.is_some() {
+        let population_index_csv = population::get_population_index_csv(&processed);
+        if !write_output(
+            "Population index CSV",
+            "population index CSV",
+            output_population_index,
+            &population_index_csv,
+            force_overwrite,
+        ) {
+            return;
+        }
+    }
+    if output_summary.is_some() {
+        let summary_csv = session::get_summary_csv(&processed);
+        write_output(
+            "Summary CSV",
+            "summary CSV",
+            output_summary,
+            &summary_csv,
+            force_overwrite,
+        );
+    }
+}
+
+// "count.csv" becomes "count-DANTH.csv" for observer DANTH.
+fn observer_path(path: &str, observer: Option<&str>) -> String {
+    let observer = match observer {
+        Some(o) => o,
+        None => return path.to_owned(),
+    };
+    let path = Path::new(path);
+    let mut file_name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    file_name.push('-');
+    file_name.push_str(observer);
+    if let Some(extension) = path.extension() {
+        file_name.push('.');
+        file_name.push_str(&extension.to_string_lossy());
     }
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
 
-    if let Some(output_distr_path) = output_distr {
-        if force_overwrite || !Path::new(&output_distr_path).exists() {
-            match fs::write(&output_distr_path, &distr_csv) {
-                Ok(_) => println!("Distribution CSV written to {}.", &output_distr_path),
+fn write_output(
+    title: &str,
+    name: &str,
+    path: Option<&str>,
+    contents: &str,
+    force_overwrite: bool,
+) -> bool {
+    if let Some(path) = path {
+        if force_overwrite || !Path::new(path).exists() {
+            match fs::write(path, contents) {
+                Ok(_) => println!("{} written to {}.", title, path),
                 Err(e) => {
-                    eprintln!("Error while writing distribution CSV: {}", e);
+                    eprintln!("Error while writing {}: {}", name, e);
+                    return false;
                 }
             }
         } else {
             println!(
-                "{} already exists and -f flag not set, outputting distribution CSV to stdout:",
-                output_distr_path
+                "{} already exists and -f flag not set, outputting {} to stdout:",
+                path, name
             );
             println!("--------------------------------------------");
-            println!("{}", distr_csv);
+            println!("{}", contents);
             println!("--------------------------------------------");
         }
     } else {
-        println!("Distribution CSV:");
+        println!("{}:", title);
         println!("--------------------------------------------");
-        println!("{}", distr_csv);
+        println!("{}", contents);
         println!("--------------------------------------------");
     }
+    true
+}
+
+fn session_from_input(
+    input_file: &str,
+    catalog: &catalog::Catalog,
+    options: &interpreter::InputOptions,
+    explain_lm: bool,
+) -> Option<Vec<session::Session>> {
+    let mut intrprtr = match interpreter::Interpreter::new(catalog) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Could not initialize Lua context. Error: {}", e);
+            return None;
+        }
+    };
+    options.apply(&mut intrprtr);
+
+    let mut source = vec![];
+    match fs::File::open(input_file) {
+        Ok(file) => {
+            let reader = BufReader::new(&file);
+            for line in reader.lines() {
+                let line_text = match line {
+                    Ok(x) => x,
+                    Err(e) => {
+                        eprintln!("Error when reading line: {}", e);
+                        return None;
+                    }
+                };
+                let result = intrprtr.execute_one_line(&line_text);
+                source.push(line_text);
+                if let Err(e) = result {
+                    eprintln!("{}", e.render("error", input_file, &source));
+                    return None;
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error when reading file: {}", e);
+            return None;
+        }
+    };
+
+    intrprtr.finish();
+    for warning in intrprtr.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    for lint in intrprtr.lints() {
+        eprintln!("{}\n", lint.render(input_file, &source));
+    }
+    if explain_lm {
+        for (line, time, explanation) in intrprtr.lm_explanations() {
+            println!("Line {} at {}: {}", line, time, source[line - 1].trim());
+            for explanation_line in explanation.to_string().lines() {
+                println!("    {}", explanation_line);
+            }
+            println!();
+        }
+    }
+
+    match intrprtr.get_sessions() {
+        Ok(s) => Some(s),
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}\n", e.render("error", input_file, &source));
+            }
+            None
+        }
+    }
+}
+
+// Prints the altitude of every area at the place and time of the `areas` subcommand.
+fn print_area_altitudes(
+    matches: &clap::ArgMatches,
+    centres: &areas::AreaCentres,
+    min_altitude: f64,
+) {
+    let latitude = matches.value_of("LAT").unwrap().parse::<f64>();
+    let longitude = matches.value_of("LON").unwrap().parse::<f64>();
+    let date = timestamp::Date::parse(matches.value_of("DATE").unwrap());
+    let time = matches.value_of("TIME").unwrap().parse::<u32>();
+    let (location, date, time) = match (latitude, longitude, date, time) {
+        (Ok(latitude), Ok(longitude), Some(date), Ok(time))
+            if (-90.0..=90.0).contains(&latitude)
+                && (-180.0..=180.0).contains(&longitude)
+                && time % 100 < 60
+                && time < 2400 =>
+        {
+            (
+                location::Location {
+                    latitude,
+                    longitude,
+                    elevation: 0_f64,
+                },
+                date,
+                timestamp::Timestamp::from_shorthand_int_notation(time),
+            )
+        }
+        _ => {
+            eprintln!("Invalid location, date or time.");
+            return;
+        }
+    };
+    let jd = timestamp::DateTime { date, time }.julian_day();
+    println!("AREA;ALT;ADVICE");
+    for area in centres.altitudes(location, jd, min_altitude) {
+        println!(
+            "{};{:.0};{}",
+            area.area.0,
+            area.altitude,
+            match area.advice {
+                areas::AreaAdvice::Recommended => "recommended",
+                areas::AreaAdvice::Usable => "usable",
+                areas::AreaAdvice::Unusable => "unusable",
+            }
+        );
+    }
+}
+
+// Runs the whole input file, continuing at the next period after an error, and prints all
+// errors and warnings. Returns whether there were no errors.
+fn check_input(
+    input_file: &str,
+    catalog: &catalog::Catalog,
+    options: &interpreter::InputOptions,
+) -> bool {
+    let source: Vec<String> = match fs::read_to_string(input_file) {
+        Ok(data) => data.lines().map(str::to_owned).collect(),
+        Err(e) => {
+            eprintln!("Error when reading file: {}", e);
+            return false;
+        }
+    };
+    let mut intrprtr = match interpreter::Interpreter::new(catalog) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Could not initialize Lua context. Error: {}", e);
+            return false;
+        }
+    };
+    options.apply(&mut intrprtr);
+
+    let mut errors = vec![];
+    for line in &source {
+        if let Err(e) = intrprtr.execute_one_line(line) {
+            errors.push(e);
+            intrprtr.recover();
+        }
+    }
+    let lints: Vec<String> = intrprtr
+        .lints()
+        .iter()
+        .map(|l| l.render(input_file, &source))
+        .collect();
+    intrprtr.finish();
+    let mut warnings: Vec<String> = intrprtr.warnings().iter().map(|w| w.to_string()).collect();
+    let period_numbers = intrprtr.period_numbers();
+    for (result, numbers) in intrprtr.into_results().into_iter().zip(period_numbers) {
+        match result {
+            Ok(session) => warnings.extend(horizon_warnings(&session, Some(&numbers), catalog)),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    for error in &errors {
+        eprintln!("{}\n", error.render("error", input_file, &source));
+    }
+    for lint in &lints {
+        eprintln!("{}\n", lint);
+    }
+    for warning in &warnings {
+        eprintln!("warning: {}\n", warning);
+    }
+    eprintln!(
+        "{}: {} error(s), {} warning(s).",
+        input_file,
+        errors.len(),
+        lints.len() + warnings.len()
+    );
+    errors.is_empty()
+}
+
+// The periods are numbered like in the diagnostics when their numbers are given, and
+// otherwise in the order of the session.
+fn horizon_warnings(
+    session: &session::Session,
+    numbers: Option<&[usize]>,
+    catalog: &catalog::Catalog,
+) -> Vec<String> {
+    let mut warnings = vec![];
+    for (i, period) in session.periods.iter().enumerate() {
+        let number = numbers.and_then(|n| n.get(i).copied()).unwrap_or(i + 1);
+        let mut altitudes: Vec<_> = period.radiant_altitudes(catalog).into_iter().collect();
+        altitudes.sort_by_key(|a| a.0.to_imo_code());
+        for (shower, altitude) in altitudes {
+            if altitude <= 0_f64 {
+                warnings.push(format!(
+                    "the radiant of {} is below the horizon in period {}{} ({:.1}°).",
+                    shower.to_imo_code(),
+                    number,
+                    match &session.header.observer {
+                        Some(observer) => format!(" of {}", observer),
+                        None => String::new(),
+                    },
+                    altitude
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+fn session_from_csvs(count_file: &str, distr_file: &str) -> Option<session::Session> {
+    let read = |path: &str| match fs::read_to_string(path) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            eprintln!("Error when reading {}: {}", path, e);
+            None
+        }
+    };
+    let count_csv = read(count_file)?;
+    let distr_csv = read(distr_file)?;
+    match import::import_session(&count_csv, &distr_csv) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            eprintln!("Error while importing CSVs. {}", e);
+            None
+        }
+    }
 }
@@ -0,0 +1,272 @@
+use crate::astro;
+use crate::builder::checkpoints_to_durations;
+use crate::factors;
+use crate::session::{LimitingMagnitude, Period, Session};
+
+impl Period {
+    // Combines this period with the next one. The time between them becomes a break, and
+    // the field and location of this period are kept.
+    pub fn merge(&self, next: &Period, max_field_distance: f64) -> Result<Period, MergeError> {
+        let mut showers = self.showers.clone();
+        let mut next_showers = next.showers.clone();
+        showers.sort_by_key(|s| s.to_imo_code());
+        next_showers.sort_by_key(|s| s.to_imo_code());
+        if showers != next_showers {
+            return Err(MergeError::DifferentShowers);
+        }
+
+        let distance =
+            astro::angular_distance(self.field.ra, self.field.dec, next.field.ra, next.field.dec);
+        if distance > max_field_distance {
+            return Err(MergeError::FieldsTooFarApart(distance));
+        }
+
+        let gap_days = next.start().julian_day() - self.end().julian_day();
+        if gap_days < 0_f64 {
+            return Err(MergeError::Overlapping);
+        }
+        if (next.end().julian_day() - self.start().julian_day()) * 1440_f64 >= 1440_f64 - 0.5 {
+            return Err(MergeError::TooLong);
+        }
+
+        let durations = |period: &Period| {
+            let c = &period.checkpoints;
+            match (
+                checkpoints_to_durations(
+                    &LimitingMagnitude::values(&c.limiting_magnitudes),
+                    period.end_time,
+                    &c.breaks,
+                ),
+                checkpoints_to_durations(&c.clouds, period.end_time, &c.breaks),
+            ) {
+                (Some(lms), Some(clouds)) if !lms.is_empty() && !clouds.is_empty() => {
+                    Ok((lms, clouds))
+                }
+                _ => Err(MergeError::NoCheckpoints),
+            }
+        };
+        let (mut lms, mut clouds) = durations(self)?;
+        let (next_lms, next_clouds) = durations(next)?;
+        lms.extend(next_lms);
+        clouds.extend(next_clouds);
+
+        let mut checkpoints = self.checkpoints.clone();
+        if next.start_time != self.end_time {
+            checkpoints.breaks.push((self.end_time, next.start_time));
+        }
+        checkpoints
+            .limiting_magnitudes
+            .extend(&next.checkpoints.limiting_magnitudes);
+        checkpoints.clouds.extend(&next.checkpoints.clouds);
+        checkpoints.breaks.extend(&next.checkpoints.breaks);
+
+        let mut meteors = self.meteors.clone();
+        meteors.extend(next.meteors.iter().cloned());
+
+        Ok(Period {
+            start_time: self.start_time,
+            end_time: next.end_time,
+            date: self.date,
+            teff: self.teff + next.teff,
+            limiting_magnitude: factors::limiting_magnitude(&lms),
+            field: self.field,
+            location: self.location,
+            cloud_factor: factors::cloud_factor(&clouds),
+            checkpoints,
+            showers: self.showers.clone(),
+            meteors,
+        })
+    }
+}
+
+impl Session {
+    // Merges consecutive periods as long as the merged period is at most `max_minutes` long.
+    // Also returns the periods (numbered from 1) that could not be merged into the period
+    // before them, and why.
+    pub fn merge_periods(
+        &self,
+        max_minutes: u32,
+        max_field_distance: f64,
+    ) -> (Session, Vec<(usize, MergeError)>) {
+        let mut periods: Vec<Period> = vec![];
+        let mut refused = vec![];
+        for (i, period) in self.periods.iter().enumerate() {
+            if let Some(last) = periods.last_mut() {
+                let minutes = (period.end().julian_day() - last.start().julian_day()) * 1440_f64;
+                if minutes <= f64::from(max_minutes) + 0.5 {
+                    match last.merge(period, max_field_distance) {
+                        Ok(merged) => {
+                            *last = merged;
+                            continue;
+                        }
+                        Err(e) => refused.push((i + 1, e)),
+                    }
+                }
+            }
+            periods.push(period.clone());
+        }
+        (
+            Session {
+                header: self.header.clone(),
+                periods,
+            },
+            refused,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    DifferentShowers,
+    FieldsTooFarApart(f64),
+    Overlapping,
+    TooLong,
+    NoCheckpoints,
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::DifferentShowers => write!(f, "The periods are not for the same showers."),
+            Self::FieldsTooFarApart(distance) => {
+                write!(f, "The fields are {:.1}° apart.", distance)
+            }
+            Self::Overlapping => write!(f, "The periods overlap."),
+            Self::TooLong => write!(f, "The merged period would be 24 hours or longer."),
+            Self::NoCheckpoints => write!(f, "The limiting magnitudes and clouds are not known."),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::meteor::Shower;
+    use crate::timestamp::Timestamp;
+
+    const INPUT: &str = "2100
+        period_start
+        date(\"12 Aug 2019\")
+        showers(PER, SPO)
+        fieldC(336, 52.3)
+        clouds(0)
+        areas(area14(10))
+        per(3) << 2108
+        period_end << 2120
+        new_period
+        period_start << 2130
+        showers(SPO, PER)
+        fieldC(330, 50)
+        clouds(20)
+        areas(area14(10))
+        spo(2) << 2140
+        period_end << 2150
+        new_period
+        period_start << 2150
+        showers(PER, SPO)
+        fieldC(20, 10)
+        clouds(0)
+        areas(area14(10))
+        period_end << 2200";
+
+    #[test]
+    fn test_merge() {
+        let session = Interpreter::test_session(INPUT);
+        let merged = session.periods[0].merge(&session.periods[1], 10.0).unwrap();
+        assert_eq!(
+            merged.start_time,
+            Timestamp {
+                hour: 21,
+                minute: 0
+            }
+        );
+        assert_eq!(
+            merged.end_time,
+            Timestamp {
+                hour: 21,
+                minute: 50
+            }
+        );
+        assert_eq!(
+            merged.teff,
+            session.periods[0].teff + session.periods[1].teff
+        );
+        // 20 minutes without clouds, 20 minutes with 20%
+        assert_eq!(merged.cloud_factor, 1.11);
+        assert_eq!(merged.field, session.periods[0].field);
+        assert_eq!(merged.meteors.len(), 2);
+        assert_eq!(
+            merged.checkpoints.breaks,
+            vec![(
+                Timestamp {
+                    hour: 21,
+                    minute: 20
+                },
+                Timestamp {
+                    hour: 21,
+                    minute: 30
+                }
+            )]
+        );
+
+        match session.periods[1].merge(&session.periods[2], 10.0) {
+            Err(MergeError::FieldsTooFarApart(_)) => {}
+            _ => panic!("merge does not return FieldsTooFarApart"),
+        }
+        assert_eq!(
+            session.periods[1].merge(&session.periods[0], 10.0).err(),
+            Some(MergeError::Overlapping)
+        );
+
+        let mut other = session.periods[1].clone();
+        other.showers = vec![Shower(*b"PER")];
+        assert_eq!(
+            session.periods[0].merge(&other, 10.0).err(),
+            Some(MergeError::DifferentShowers)
+        );
+    }
+
+    #[test]
+    fn test_merge_periods() {
+        let session = Interpreter::test_session(INPUT);
+        let (merged, refused) = session.merge_periods(60, 10.0);
+        assert_eq!(merged.periods.len(), 2);
+        assert_eq!(merged.periods[0].teff, 40_f64 / 60_f64);
+        assert_eq!(refused.len(), 1);
+        assert_eq!(refused[0].0, 3);
+
+        let (merged, refused) = session.merge_periods(20, 10.0);
+        assert_eq!(merged.periods.len(), 3);
+        assert!(refused.is_empty());
+    }
+
+    #[test]
+    fn test_merge_periods_next_night() {
+        // The second period is a day later, so it is not merged even though its end
+        // time is only 30 minutes after the start of the first one.
+        let session = Interpreter::test_session(
+            "2100
+            period_start
+            date(\"12 Aug 2019\")
+            showers(PER, SPO)
+            fieldC(336, 52.3)
+            clouds(0)
+            lm(6.2)
+            period_end << 2120
+            new_period
+            period_start << 2110
+            date(\"13 Aug 2019\")
+            showers(PER, SPO)
+            fieldC(336, 52.3)
+            clouds(0)
+            lm(6.2)
+            period_end << 2130",
+        );
+        let (merged, refused) = session.merge_periods(60, 10.0);
+        assert_eq!(merged.periods.len(), 2);
+        assert!(refused.is_empty());
+    }
+}
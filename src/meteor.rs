@@ -1,69 +1,114 @@
-#[derive(Copy, Clone, PartialEq, Debug)]
+use crate::timestamp::Timestamp;
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct Meteor {
     pub shower: Shower,
     pub magnitude: i32,
+    // When the meteor was seen and on which line of the input, if known. Meteors read
+    // from a distribution CSV have neither.
+    pub time: Option<Timestamp>,
+    pub line: Option<usize>,
+    pub attributes: MeteorAttributes,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub enum Shower {
-    Quadrantids,
-    Lyrids,
-    EtaAquarids,
-    JuneBootids,
-    DeltaAquariids,
-    AlphaCapricornids,
-    Perseids,
-    KappaCygnids,
-    AlphaAurigids,
-    SeptemberEpsilonPerseids,
-    OctoberCameloparalids,
-    Draconids,
-    EpsilonGeminids,
-    Orionids,
-    SouthernTaurids,
-    NorthernTaurids,
-    Leonids,
-    DecemberAlphaDraconids,
-    Monocerotids,
-    SigmaHydrids,
-    Geminids,
-    DecemberLeoMinorids,
-    ComaBerenicids,
-    Ursids,
-    Antihelion,
-    Sporadic,
+// Meteors of this magnitude (in tenths) or brighter are fireballs, which are reported to the
+// IMO separately.
+pub const FIREBALL_MAGNITUDE: i32 = -30;
+
+impl Meteor {
+    pub fn is_fireball(&self) -> bool {
+        self.magnitude <= FIREBALL_MAGNITUDE
+    }
+}
+
+// What else was noted about a meteor, all optional.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MeteorAttributes {
+    // How long the train was visible, in seconds.
+    pub train: Option<f64>,
+    pub color: Option<String>,
+    pub note: Option<String>,
+}
+
+impl MeteorAttributes {
+    pub fn has_train(&self) -> bool {
+        match self.train {
+            Some(seconds) => seconds > 0_f64,
+            None => false,
+        }
+    }
+
+    // The train, colour and note as CSV fields, empty when not given.
+    pub fn csv_fields(&self) -> String {
+        let text = |t: &Option<String>| t.as_deref().map_or(String::new(), csv_text);
+        format!(
+            "{};{};{}",
+            match self.train {
+                Some(seconds) => seconds.to_string(),
+                None => String::new(),
+            },
+            text(&self.color),
+            text(&self.note)
+        )
+    }
+}
+
+// Free text as a CSV field. A semicolon in the text would start a new field, so it becomes
+// a comma.
+pub fn csv_text(text: &str) -> String {
+    text.replace(';', ",")
 }
 
-use Shower::*;
+// A shower, identified by its three-letter IMO code. Everything else about
+// the shower is found in the catalog.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Shower(pub [u8; 3]);
+
 impl Shower {
-    pub fn to_imo_code(&self) -> &str {
-        match self {
-            Quadrantids => "QUA",
-            Lyrids => "LYR",
-            EtaAquarids => "ETA",
-            JuneBootids => "JBO",
-            DeltaAquariids => "SDA",
-            AlphaCapricornids => "CAP",
-            Perseids => "PER",
-            KappaCygnids => "KCG",
-            AlphaAurigids => "AUR",
-            SeptemberEpsilonPerseids => "SPE",
-            OctoberCameloparalids => "OCT",
-            Draconids => "DRA",
-            EpsilonGeminids => "EGE",
-            Orionids => "ORI",
-            SouthernTaurids => "STA",
-            NorthernTaurids => "NTA",
-            Leonids => "LEO",
-            DecemberAlphaDraconids => "DAD",
-            Monocerotids => "MON",
-            SigmaHydrids => "HYD",
-            Geminids => "GEM",
-            DecemberLeoMinorids => "DLM",
-            ComaBerenicids => "COM",
-            Ursids => "URS",
-            Antihelion => "ANT",
-            Sporadic => "SPO",
+    pub const SPORADIC: Self = Self(*b"SPO");
+
+    pub fn from_imo_code(code: &str) -> Option<Self> {
+        let bytes = code.as_bytes();
+        if bytes.len() == 3 && bytes.iter().all(u8::is_ascii_uppercase) {
+            Some(Self([bytes[0], bytes[1], bytes[2]]))
+        } else {
+            None
         }
     }
+
+    pub fn to_imo_code(self) -> String {
+        String::from_utf8(self.0.to_vec())
+            .expect("[supposedly unreachable] Shower code is not ASCII")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shower_code_1() {
+        assert_eq!(Shower::from_imo_code("PER"), Some(Shower(*b"PER")));
+        assert_eq!(Shower(*b"PER").to_imo_code(), "PER");
+    }
+
+    #[test]
+    fn test_shower_code_2() {
+        assert_eq!(Shower::from_imo_code("per"), None);
+        assert_eq!(Shower::from_imo_code("PERS"), None);
+        assert_eq!(Shower::from_imo_code("P1R"), None);
+    }
+
+    #[test]
+    fn test_attributes_csv_fields() {
+        let attributes = MeteorAttributes {
+            train: Some(2.5),
+            color: None,
+            note: Some("flare; fragmented".to_owned()),
+        };
+        assert!(attributes.has_train());
+        assert_eq!(attributes.csv_fields(), "2.5;;flare, fragmented");
+        assert_eq!(MeteorAttributes::default().csv_fields(), ";;");
+        assert!(!MeteorAttributes::default().has_train());
+    }
 }
@@ -0,0 +1,335 @@
+use crate::meteor::Shower;
+use crate::session::{Period, Session};
+use std::collections::{BTreeMap, HashMap};
+
+// Only magnitude classes at least this much brighter than Lm are used: fainter meteors are
+// missed too often for the distribution to be complete.
+const LM_MARGIN: f64 = 1.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PopulationIndex {
+    pub r: f64,
+    pub error: f64,
+    // The number of magnitude classes in the fit.
+    pub classes: usize,
+}
+
+// The cumulative number of meteors of each magnitude class or brighter, summed over the
+// periods with the classes taken relative to the Lm of their period. The classes of the
+// first period set a grid of m - Lm; ln N of the other periods is interpolated onto it,
+// and extrapolated by at most half a class past their brightest and faintest classes.
+fn cumulative_counts<'a, I>(periods: I, shower: Shower) -> BTreeMap<i32, f64>
+where
+    I: Iterator<Item = &'a Period>,
+{
+    let mut sums = BTreeMap::new();
+    // The grid is at class + offset.
+    let mut offset = None;
+    for period in periods {
+        let points = relative_log_counts(period, shower);
+        if points.is_empty() {
+            continue;
+        }
+        let offset = *offset.get_or_insert(points[0].0 - points[0].0.floor());
+        let first = (points[0].0 - 0.5 - offset).ceil() as i32;
+        let last = (points[points.len() - 1].0 + 0.5 - offset).floor() as i32;
+        for class in first..=last {
+            let x = f64::from(class) + offset;
+            if x > -LM_MARGIN + 1e-9 {
+                break;
+            }
+            *sums.entry(class).or_insert(0_f64) += interpolate(&points, x).exp();
+        }
+    }
+    sums
+}
+
+// The points (m - Lm, ln N(m)) of a period for the classes with meteors that are bright
+// enough, from bright to faint.
+fn relative_log_counts(period: &Period, shower: Shower) -> Vec<(f64, f64)> {
+    let counts = period.get_count_and_distribution();
+    let distribution = match counts.get(&shower) {
+        Some((_, distribution)) => distribution.to_map(),
+        None => return vec![],
+    };
+    let mut points = vec![];
+    let mut cumulative = 0;
+    for magnitude in -6..=7 {
+        cumulative += distribution[&magnitude];
+        let magnitude = f64::from(magnitude);
+        if cumulative > 0 && magnitude <= period.limiting_magnitude - LM_MARGIN {
+            // The distribution counts in tenths of meteors.
+            let count = f64::from(cumulative) / 10_f64;
+            points.push((magnitude - period.limiting_magnitude, count.ln()));
+        }
+    }
+    points
+}
+
+// Linear interpolation between the points around `x`, or along the first or last two
+// points outside of them.
+fn interpolate(points: &[(f64, f64)], x: f64) -> f64 {
+    if points.len() == 1 {
+        return points[0].1;
+    }
+    let i = points
+        .iter()
+        .skip(1)
+        .position(|p| p.0 >= x)
+        .unwrap_or(points.len() - 2);
+    let (a, b) = (points[i], points[i + 1]);
+    a.1 + (b.1 - a.1) * (x - a.0) / (b.0 - a.0)
+}
+
+// IMO method: ln N(m) = a + m ln(r) for the cumulative counts N(m), fitted with least
+// squares. The error follows from the standard error of the slope, so at least three
+// classes with meteors are needed.
+fn fit(cumulative: &BTreeMap<i32, f64>) -> Option<PopulationIndex> {
+    let points: Vec<(f64, f64)> = cumulative
+        .iter()
+        .filter(|(_, count)| **count > 0_f64)
+        .map(|(class, count)| (f64::from(*class), count.ln()))
+        .collect();
+    let n = points.len() as f64;
+    if points.len() < 3 {
+        return None;
+    }
+
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residuals: f64 = points
+        .iter()
+        .map(|p| (p.1 - intercept - slope * p.0).powi(2))
+        .sum();
+    let slope_error = (residuals / (n - 2_f64) / sxx).sqrt();
+
+    let r = slope.exp();
+    Some(PopulationIndex {
+        r,
+        error: r * slope_error,
+        classes: points.len(),
+    })
+}
+
+impl Period {
+    pub fn population_index(&self, shower: Shower) -> Option<PopulationIndex> {
+        fit(&cumulative_counts(std::iter::once(self), shower))
+    }
+}
+
+impl Session {
+    // The population index from the meteors of all periods together.
+    pub fn population_index(&self, shower: Shower) -> Option<PopulationIndex> {
+        fit(&cumulative_counts(self.periods.iter(), shower))
+    }
+
+    // The population index of every shower for which there are enough meteors.
+    pub fn population_indices(&self) -> HashMap<Shower, f64> {
+        sorted_showers(self)
+            .into_iter()
+            .filter_map(|shower| self.population_index(shower).map(|p| (shower, p.r)))
+            .collect()
+    }
+}
+
+fn sorted_showers(session: &Session) -> Vec<Shower> {
+    let mut showers: Vec<Shower> = session.all_showers().into_iter().collect();
+    showers.sort_by_key(|s| s.to_imo_code());
+    showers
+}
+
+pub fn population_index(sessions: &[Session], shower: Shower) -> Option<PopulationIndex> {
+    fit(&cumulative_counts(
+        sessions.iter().flat_map(|s| s.periods.iter()),
+        shower,
+    ))
+}
+
+// The population index per observer, shower and period, then per observer and shower for
+// all their periods, and with several observers also per shower for everyone together.
+pub fn get_population_index_csv(sessions: &[Session]) -> String {
+    let line = |observer: &str, shower: Shower, period: &str, p: Option<PopulationIndex>| match p {
+        Some(p) => format!(
+            "{};{};{};{:.2};{:.2};{}",
+            observer,
+            shower.to_imo_code(),
+            period,
+            p.r,
+            p.error,
+            p.classes
+        ),
+        None => format!("{};{};{};-;-;-", observer, shower.to_imo_code(), period),
+    };
+
+    let mut lines = vec!["OBSERVER;SHOWER;PERIOD;r;ERR;CLASSES".to_owned()];
+    let mut all_showers: Vec<Shower> = vec![];
+    for session in sessions {
+        let observer = session.header.observer.as_ref().map_or("", String::as_str);
+        for shower in sorted_showers(session) {
+            for (i, period) in session.periods.iter().enumerate() {
+                if period.showers.contains(&shower) {
+                    lines.push(line(
+                        observer,
+                        shower,
+                        &(i + 1).to_string(),
+                        period.population_index(shower),
+                    ));
+                }
+            }
+            lines.push(line(
+                observer,
+                shower,
+                "ALL",
+                session.population_index(shower),
+            ));
+            if !all_showers.contains(&shower) {
+                all_showers.push(shower);
+            }
+        }
+    }
+    if sessions.len() > 1 {
+        all_showers.sort_by_key(|s| s.to_imo_code());
+        for shower in all_showers {
+            lines.push(line(
+                "ALL",
+                shower,
+                "ALL",
+                population_index(sessions, shower),
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Field;
+    use crate::meteor::{Meteor, MeteorAttributes};
+    use crate::session::{Checkpoints, SessionHeader};
+    use crate::timestamp::{Date, Timestamp};
+
+    // A period with `counts[i]` meteors of magnitude `first + i`.
+    fn period(limiting_magnitude: f64, first: i32, counts: &[usize]) -> Period {
+        let mut meteors = vec![];
+        for (i, count) in counts.iter().enumerate() {
+            for _ in 0..*count {
+                meteors.push(Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: (first + i as i32) * 10,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                });
+            }
+        }
+        Period {
+            start_time: Timestamp { hour: 0, minute: 0 },
+            end_time: Timestamp { hour: 1, minute: 0 },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 13,
+            },
+            teff: 1.0,
+            limiting_magnitude,
+            field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower::SPORADIC],
+            meteors,
+        }
+    }
+
+    fn round(a: f64) -> f64 {
+        (a * 1000_f64).round() / 1000_f64
+    }
+
+    #[test]
+    fn test_population_index() {
+        // The cumulative counts double with every magnitude: 1, 2, 4, 8, 16, 32.
+        let exact = period(6.5, 0, &[1, 1, 2, 4, 8, 16, 50]);
+        let p = exact.population_index(Shower(*b"PER")).unwrap();
+        assert_eq!(round(p.r), 2.0);
+        assert_eq!(round(p.error), 0.0);
+        // Magnitude 6 is fainter than Lm - 1, so it is not used.
+        assert_eq!(p.classes, 6);
+
+        let noisy = period(6.0, 1, &[2, 3, 5, 14, 20]);
+        let p = noisy.population_index(Shower(*b"PER")).unwrap();
+        assert!(p.r > 2.0 && p.r < 3.0);
+        assert!(p.error > 0.0);
+        assert_eq!(noisy.population_index(Shower::SPORADIC), None);
+        assert_eq!(
+            period(6.0, 4, &[5, 10]).population_index(Shower(*b"PER")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_population_index_aggregated() {
+        // With Lm 5.5 instead of 6.5, the same distribution one magnitude brighter.
+        let a = period(6.5, 0, &[1, 1, 2, 4, 8, 16]);
+        let b = period(5.5, -1, &[1, 1, 2, 4, 8, 16]);
+        let session = Session {
+            header: SessionHeader::default(),
+            periods: vec![a, b],
+        };
+        let p = session.population_index(Shower(*b"PER")).unwrap();
+        assert_eq!(round(p.r), 2.0);
+        assert_eq!(
+            session
+                .population_indices()
+                .get(&Shower(*b"PER"))
+                .map(|r| round(*r)),
+            Some(2.0)
+        );
+
+        let other = Session {
+            header: SessionHeader {
+                observer: Some("BBBBB".to_owned()),
+                site: None,
+            },
+            periods: vec![period(6.5, 0, &[1, 1, 2, 4, 8, 16])],
+        };
+        // Lm 6.49 would round to other classes than Lm 6.5.
+        let rounded_apart = Session {
+            header: SessionHeader::default(),
+            periods: vec![
+                period(6.5, 0, &[1, 1, 2, 4, 8, 16]),
+                period(6.49, 0, &[1, 1, 2, 4, 8, 16]),
+            ],
+        };
+        let p = rounded_apart.population_index(Shower(*b"PER")).unwrap();
+        assert_eq!(round(p.r), 2.0);
+        assert_eq!(round(p.error), 0.0);
+        assert_eq!(p.classes, 6);
+
+        let sessions = [session, other];
+        assert_eq!(
+            round(population_index(&sessions, Shower(*b"PER")).unwrap().r),
+            2.0
+        );
+        assert_eq!(
+            get_population_index_csv(&sessions),
+            "OBSERVER;SHOWER;PERIOD;r;ERR;CLASSES
+;PER;1;2.00;0.00;6
+;PER;2;2.00;0.00;6
+;PER;ALL;2.00;0.00;6
+;SPO;1;-;-;-
+;SPO;2;-;-;-
+;SPO;ALL;-;-;-
+BBBBB;PER;1;2.00;0.00;6
+BBBBB;PER;ALL;2.00;0.00;6
+BBBBB;SPO;1;-;-;-
+BBBBB;SPO;ALL;-;-;-
+ALL;PER;ALL;2.00;0.00;6
+ALL;SPO;ALL;-;-;-"
+        );
+    }
+}
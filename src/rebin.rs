@@ -0,0 +1,307 @@
+use crate::astro;
+use crate::builder::checkpoints_to_durations;
+use crate::factors;
+use crate::meteor::Meteor;
+use crate::session::{Checkpoints, LimitingMagnitude, Period, Session};
+use crate::timestamp::{self, Timestamp};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Binning {
+    // Slices that start and end at a multiple of this many minutes after 0h UT.
+    Minutes(u32),
+    // Slices that start and end when the solar longitude passes a multiple of this step.
+    SolarLongitude(f64),
+}
+
+impl Session {
+    // Splits every period into slices. Slices that fall entirely in a break are left out.
+    pub fn rebin(&self, binning: Binning) -> Result<Session, RebinError> {
+        let mut periods = vec![];
+        for (i, period) in self.periods.iter().enumerate() {
+            periods.extend(period.rebin(binning, i + 1)?);
+        }
+        Ok(Session {
+            header: self.header.clone(),
+            periods,
+        })
+    }
+}
+
+impl Period {
+    // The minutes after the start of the period at which a new slice starts.
+    fn bin_boundaries(&self, binning: Binning) -> Vec<u32> {
+        let length = self.end_time - self.start_time;
+        let solar_longitude_bin = |minutes: u32, step: f64| {
+            let time = self.resolve(self.start_time.plus_minutes(minutes));
+            (astro::solar_longitude(time.julian_day()) / step).floor()
+        };
+        (1..length)
+            .filter(|m| match binning {
+                Binning::Minutes(n) => {
+                    let time = self.start_time.plus_minutes(*m);
+                    (time.hour * 60 + time.minute).checked_rem(n) == Some(0)
+                }
+                Binning::SolarLongitude(step) => {
+                    solar_longitude_bin(*m, step) != solar_longitude_bin(*m - 1, step)
+                }
+            })
+            .collect()
+    }
+
+    fn rebin(&self, binning: Binning, number: usize) -> Result<Vec<Period>, RebinError> {
+        let checkpoints = &self.checkpoints;
+        if checkpoints.limiting_magnitudes.is_empty() || checkpoints.clouds.is_empty() {
+            return Err(RebinError::NoCheckpoints(number));
+        }
+        let offset = |time: Timestamp| time - self.start_time;
+        let length = offset(self.end_time);
+
+        let mut meteors: Vec<(u32, Meteor)> = vec![];
+        for meteor in &self.meteors {
+            match meteor.time.map(offset) {
+                Some(t) if t <= length => meteors.push((t, meteor.clone())),
+                Some(_) => return Err(RebinError::MeteorOutsidePeriod(number)),
+                None => return Err(RebinError::MeteorWithoutTime(number)),
+            }
+        }
+        let breaks: Vec<(u32, u32)> = checkpoints
+            .breaks
+            .iter()
+            .map(|b| (offset(b.0), offset(b.1)))
+            .collect();
+
+        let mut edges = vec![0];
+        edges.extend(self.bin_boundaries(binning));
+        edges.push(length);
+
+        let mut slices = vec![];
+        for (i, edge) in edges.windows(2).enumerate() {
+            let (bin_start, bin_end) = (edge[0], edge[1]);
+            let last = i == edges.len() - 2;
+            let slice_meteors: Vec<Meteor> = meteors
+                .iter()
+                .filter(|(t, _)| *t >= bin_start && (*t < bin_end || (last && *t == bin_end)))
+                .map(|(_, m)| m.clone())
+                .collect();
+
+            // A slice that starts or ends in a break is shortened to the observing time.
+            let (mut start, mut end) = (bin_start, bin_end);
+            for (break_start, break_end) in &breaks {
+                if *break_start <= start && start < *break_end {
+                    start = *break_end;
+                }
+                if *break_start < end && end <= *break_end {
+                    end = *break_start;
+                }
+            }
+            if start >= end {
+                if !slice_meteors.is_empty() {
+                    return Err(RebinError::MeteorOutsidePeriod(number));
+                }
+                continue;
+            }
+
+            slices.push(self.slice(start, end, slice_meteors, number)?);
+        }
+        Ok(slices)
+    }
+
+    // The part of the period from `start` to `end` minutes after its start.
+    fn slice(
+        &self,
+        start: u32,
+        end: u32,
+        meteors: Vec<Meteor>,
+        number: usize,
+    ) -> Result<Period, RebinError> {
+        let offset = |time: Timestamp| time - self.start_time;
+        let start_time = self.start_time.plus_minutes(start);
+        let end_time = self.start_time.plus_minutes(end);
+
+        let breaks: Vec<(Timestamp, Timestamp)> = self
+            .checkpoints
+            .breaks
+            .iter()
+            .filter(|b| start < offset(b.0) && offset(b.1) < end)
+            .copied()
+            .collect();
+        let limiting_magnitudes = slice_checkpoints(
+            &self.checkpoints.limiting_magnitudes,
+            offset,
+            start,
+            end,
+            start_time,
+        );
+        let clouds = slice_checkpoints(&self.checkpoints.clouds, offset, start, end, start_time);
+
+        let teff_minutes = timestamp::effective_time_minutes(start_time, end_time, &breaks)
+            .ok_or(RebinError::InvalidBreaks(number))?;
+        let lms = checkpoints_to_durations(
+            &LimitingMagnitude::values(&limiting_magnitudes),
+            end_time,
+            &breaks,
+        )
+        .ok_or(RebinError::InvalidBreaks(number))?;
+        let cloud_durations = checkpoints_to_durations(&clouds, end_time, &breaks)
+            .ok_or(RebinError::InvalidBreaks(number))?;
+
+        Ok(Period {
+            start_time,
+            end_time,
+            date: self.resolve(start_time).date,
+            teff: f64::from(teff_minutes) / 60_f64,
+            limiting_magnitude: factors::limiting_magnitude(&lms),
+            field: self.field,
+            location: self.location,
+            cloud_factor: factors::cloud_factor(&cloud_durations),
+            checkpoints: Checkpoints {
+                limiting_magnitudes,
+                clouds,
+                breaks,
+            },
+            showers: self.showers.clone(),
+            meteors,
+        })
+    }
+}
+
+// The checkpoints that apply between `start` and `end` (in minutes after the start of the
+// period), beginning with the one that is valid at `start`, moved to `start_time`.
+fn slice_checkpoints<T: Copy>(
+    checkpoints: &[(T, Timestamp)],
+    offset: impl Fn(Timestamp) -> u32,
+    start: u32,
+    end: u32,
+    start_time: Timestamp,
+) -> Vec<(T, Timestamp)> {
+    let current = checkpoints
+        .iter()
+        .filter(|c| offset(c.1) <= start)
+        .last()
+        .unwrap_or(&checkpoints[0]);
+    let mut result = vec![(current.0, start_time)];
+    result.extend(
+        checkpoints
+            .iter()
+            .filter(|c| start < offset(c.1) && offset(c.1) < end),
+    );
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebinError {
+    NoCheckpoints(usize),
+    MeteorWithoutTime(usize),
+    MeteorOutsidePeriod(usize),
+    InvalidBreaks(usize),
+}
+
+impl std::fmt::Display for RebinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (period, message) = match self {
+            Self::NoCheckpoints(p) => (p, "The limiting magnitudes and clouds are not known."),
+            Self::MeteorWithoutTime(p) => (p, "The time of a meteor is not known."),
+            Self::MeteorOutsidePeriod(p) => {
+                (p, "A meteor is outside the observing time of the period.")
+            }
+            Self::InvalidBreaks(p) => (p, "The breaks do not adhere to the break rules."),
+        };
+        write!(f, "Period {}: {}", period, message)
+    }
+}
+
+impl std::error::Error for RebinError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::meteor::Shower;
+    use crate::session::LmMethod;
+
+    const INPUT: &str = "2105
+        period_start
+        date(\"12 Aug 2019\")
+        showers(PER, SPO)
+        fieldC(336, 52.3)
+        clouds(0)
+        areas(area14(10))
+        per(3) << 2108
+        spo(2) << 2112
+        clouds(10) << 2115
+        per(1) << 2125
+        break_start << 2126
+        break_end << 2134
+        areas(area14(8)) << 2135
+        per(2) << 2140
+        period_end << 2140";
+
+    #[test]
+    fn test_rebin_minutes() {
+        let rebinned = Interpreter::test_session(INPUT)
+            .rebin(Binning::Minutes(10))
+            .unwrap();
+        let periods = &rebinned.periods;
+        let times: Vec<(u32, u32)> = periods
+            .iter()
+            .map(|p| {
+                (
+                    p.start_time.to_shorthand_int_notation(),
+                    p.end_time.to_shorthand_int_notation(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            times,
+            vec![(2105, 2110), (2110, 2120), (2120, 2126), (2134, 2140)]
+        );
+        let counts: Vec<usize> = periods.iter().map(|p| p.meteors.len()).collect();
+        assert_eq!(counts, vec![1, 1, 1, 1]);
+        assert_eq!(periods[3].meteors[0].shower, Shower(*b"PER"));
+        assert_eq!(periods[2].teff, 0.1);
+
+        // 5 minutes at 0% and 5 minutes at 10%
+        assert_eq!(periods[1].cloud_factor, 1.05);
+        assert_eq!(periods[2].cloud_factor, 1.11);
+        assert_eq!(periods[3].checkpoints.limiting_magnitudes.len(), 2);
+        assert!(periods[3]
+            .checkpoints
+            .limiting_magnitudes
+            .iter()
+            .all(|c| c.0.method == LmMethod::Areas));
+        assert!(periods[3].limiting_magnitude < periods[0].limiting_magnitude);
+    }
+
+    #[test]
+    fn test_rebin_solar_longitude() {
+        let session = Interpreter::test_session(INPUT);
+        let rebinned = session.rebin(Binning::SolarLongitude(0.01)).unwrap();
+        let teff: f64 = rebinned.periods.iter().map(|p| p.teff).sum();
+        assert_eq!(
+            (teff * 60_f64).round(),
+            (session.periods[0].teff * 60_f64).round()
+        );
+        assert!(rebinned.periods.len() >= 2);
+        let meteors: usize = rebinned.periods.iter().map(|p| p.meteors.len()).sum();
+        assert_eq!(meteors, 4);
+    }
+
+    #[test]
+    fn test_rebin_errors() {
+        let mut session = Interpreter::test_session(INPUT);
+        session.periods[0].meteors[1].time = None;
+        assert_eq!(
+            session.rebin(Binning::Minutes(10)).err(),
+            Some(RebinError::MeteorWithoutTime(1))
+        );
+        session.periods[0].checkpoints.clouds.clear();
+        assert_eq!(
+            session
+                .rebin(Binning::Minutes(10))
+                .err()
+                .unwrap()
+                .to_string(),
+            "Period 1: The limiting magnitudes and clouds are not known."
+        );
+    }
+}
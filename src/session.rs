@@ -1,8 +1,11 @@
 use crate::areas::Area;
+use crate::astro;
+use crate::catalog::Catalog;
 use crate::distribution::Distribution;
 use crate::field::Field;
-use crate::meteor::{Meteor, Shower};
-use crate::timestamp::Timestamp;
+use crate::location::{Location, Site};
+use crate::meteor::{csv_text, Meteor, Shower};
+use crate::timestamp::{Date, DateTime, Timestamp};
 use std::collections::{HashMap, HashSet};
 use std::option::NoneError;
 
@@ -15,30 +18,114 @@ pub enum Event {
     NewPeriod,
     Meteor(Meteor),
     Field(Field),
+    Location(Location),
     PeriodStart,
     PeriodEnd,
-    PeriodDate(String),
+    PeriodDate(Date),
     Showers(Vec<Shower>),
+    LimitingMagnitude(f64),
+    Sqm(f64),
+    Observer(String),
+    Site(Site),
 }
 
-pub struct TimestampedEvent(pub Timestamp, pub Event);
+impl Event {
+    // Whether the event is part of the session header instead of a period.
+    pub fn is_header(&self) -> bool {
+        matches!(self, Event::Observer(_) | Event::Site(_))
+    }
+}
+
+// An event, with the time at which it happened and the line of the input it comes from.
+pub struct TimestampedEvent(pub Timestamp, pub Event, pub usize);
 
+#[derive(Clone)]
 pub struct Period {
     pub start_time: Timestamp,
     pub end_time: Timestamp,
-    pub date: String,
+    pub date: Date,
     pub teff: f64,
     pub limiting_magnitude: f64,
     pub field: Field,
+    pub location: Option<Location>,
     pub cloud_factor: f64,
+    pub checkpoints: Checkpoints,
     pub showers: Vec<Shower>,
     pub meteors: Vec<Meteor>,
 }
 
+// The limiting magnitudes and cloud estimates as they were recorded during a period, each
+// valid from its time until the next one, and the breaks.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Checkpoints {
+    pub limiting_magnitudes: Vec<(LimitingMagnitude, Timestamp)>,
+    pub clouds: Vec<(u8, Timestamp)>,
+    pub breaks: Vec<(Timestamp, Timestamp)>,
+}
+
+// A limiting magnitude checkpoint and how it was found.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LimitingMagnitude {
+    pub value: f64,
+    pub method: LmMethod,
+}
+
+impl LimitingMagnitude {
+    // Just the values, to weigh them by how long they were valid.
+    pub fn values(checkpoints: &[(Self, Timestamp)]) -> Vec<(f64, Timestamp)> {
+        checkpoints.iter().map(|c| (c.0.value, c.1)).collect()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LmMethod {
+    // Stars counted in areas, with `areas`.
+    Areas,
+    // Estimated directly, with `lm`.
+    Estimate,
+    // The average of a period read from a count CSV.
+    Imported,
+    // Converted from this sky quality meter reading, with `sqm`.
+    Sqm(f64),
+}
+
+impl LmMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Areas => "areas",
+            Self::Estimate => "estimate",
+            Self::Imported => "imported",
+            Self::Sqm(_) => "sqm",
+        }
+    }
+
+    pub fn sqm_reading(self) -> Option<f64> {
+        match self {
+            Self::Sqm(reading) => Some(reading),
+            _ => None,
+        }
+    }
+}
+
 pub struct Session {
+    pub header: SessionHeader,
     pub periods: Vec<Period>,
 }
 
+// Who observed and where. This is given once for the whole input file.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SessionHeader {
+    pub observer: Option<String>,
+    pub site: Option<Site>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SolarLongitudes {
+    pub start: f64,
+    pub middle: f64,
+    pub end: f64,
+}
+
 impl Period {
     pub fn get_count_and_distribution(&self) -> HashMap<Shower, (u32, Distribution)> {
         let mut map = HashMap::new();
@@ -49,7 +136,57 @@ impl Period {
         for meteor in &self.meteors {
             let mut cd = map.get_mut(&meteor.shower).unwrap();
             cd.0 += 1;
-            cd.1.add_meteor(*meteor);
+            cd.1.add_meteor(meteor.clone());
+        }
+        map
+    }
+
+    pub fn start(&self) -> DateTime {
+        DateTime {
+            date: self.date,
+            time: self.start_time,
+        }
+    }
+
+    pub fn end(&self) -> DateTime {
+        self.resolve(self.end_time)
+    }
+
+    // The moment within this period at which the clock showed the given time.
+    pub fn resolve(&self, time: Timestamp) -> DateTime {
+        self.start().resolve(time)
+    }
+
+    pub fn midpoint_julian_day(&self) -> f64 {
+        (self.start().julian_day() + self.end().julian_day()) / 2_f64
+    }
+
+    pub fn solar_longitudes(&self) -> SolarLongitudes {
+        SolarLongitudes {
+            start: astro::solar_longitude(self.start().julian_day()),
+            middle: astro::solar_longitude(self.midpoint_julian_day()),
+            end: astro::solar_longitude(self.end().julian_day()),
+        }
+    }
+
+    // Altitude of the radiant of every observed shower at the midpoint of the period.
+    // Empty if the location of the period is unknown.
+    pub fn radiant_altitudes(&self, catalog: &Catalog) -> HashMap<Shower, f64> {
+        let mut map = HashMap::new();
+        if let Some(location) = self.location {
+            let jd = self.midpoint_julian_day();
+            let solar_longitude = astro::solar_longitude(jd);
+            for shower in &self.showers {
+                let radiant = catalog
+                    .get(*shower)
+                    .and_then(|info| info.radiant_at(solar_longitude));
+                if let Some((ra, dec)) = radiant {
+                    map.insert(
+                        *shower,
+                        astro::altitude(ra, dec, location.latitude, location.longitude, jd),
+                    );
+                }
+            }
         }
         map
     }
@@ -61,7 +198,7 @@ impl Period {
         let mut lines: Vec<String> = vec![];
 
         let mut showers_sorted = self.showers.clone();
-        showers_sorted.sort_by(|a, b| a.to_imo_code().cmp(b.to_imo_code()));
+        showers_sorted.sort_by_key(|s| s.to_imo_code());
 
         for shower in showers_sorted {
             let shower_dist = count_and_dist.get(&shower)?.1.to_map();
@@ -91,8 +228,29 @@ impl Period {
     }
 }
 
+impl SessionHeader {
+    // The observer, site name and site coordinates, with empty fields for what is unknown.
+    pub fn csv_fields(&self) -> Option<String> {
+        if self.observer.is_none() && self.site.is_none() {
+            return None;
+        }
+        let observer = csv_text(self.observer.as_deref().unwrap_or(""));
+        Some(match &self.site {
+            Some(site) => format!(
+                "{};{};{};{};{}",
+                observer,
+                csv_text(&site.name),
+                site.location.latitude,
+                site.location.longitude,
+                site.location.elevation
+            ),
+            None => format!("{};;;;", observer),
+        })
+    }
+}
+
 impl Session {
-    fn all_showers(&self) -> HashSet<Shower> {
+    pub fn all_showers(&self) -> HashSet<Shower> {
         let mut result = HashSet::new();
         for period in &self.periods {
             for shower in &period.showers {
@@ -102,28 +260,48 @@ impl Session {
         result
     }
 
-    pub fn get_csvs(&self) -> Result<(String, String), NoneError> {
+    fn count_csv(&self, extended: bool) -> String {
         let mut showers: Vec<Shower> = self.all_showers().into_iter().collect();
-        showers.sort_by(|a, b| a.to_imo_code().cmp(b.to_imo_code()));
+        showers.sort_by_key(|s| s.to_imo_code());
+
+        // The extended CSV also has the header, if there is one.
+        let header = if extended {
+            self.header.csv_fields()
+        } else {
+            None
+        };
 
         let mut count_csv_parts: Vec<String> = vec![];
         count_csv_parts.push(format!(
-            "DATE UT;START;END;Teff;RA;Dec;F;Lm;{}",
+            "DATE UT;START;END;Teff;RA;Dec;F;Lm;{}{}{}",
+            if header.is_some() {
+                "OBSERVER;SITE;LAT;LON;ELEV;"
+            } else {
+                ""
+            },
+            if extended {
+                "SOL START;SOL MID;SOL END;"
+            } else {
+                ""
+            },
             &showers
                 .iter()
-                .map(Shower::to_imo_code)
-                .collect::<Vec<&str>>()
+                .map(|s| s.to_imo_code())
+                .collect::<Vec<String>>()
                 .join(";;")
         ));
 
-        let mut distr_csv_parts: Vec<String> = vec![];
-        distr_csv_parts
-            .push("DATE UT;START;END;SHOWER;-6;-5;-4;-3;-2;-1;0;1;2;3;4;5;6;7".to_owned());
         for period in &self.periods {
             let count_and_dist = period.get_count_and_distribution();
-            distr_csv_parts.push(period.get_distribution_csv(&count_and_dist)?);
 
             let mut count_parts: Vec<String> = vec![];
+            if let Some(header) = &header {
+                count_parts.push(header.clone());
+            }
+            if extended {
+                let sl = period.solar_longitudes();
+                count_parts.push(format!("{:.3};{:.3};{:.3}", sl.start, sl.middle, sl.end));
+            }
             for shower in &showers {
                 match count_and_dist.get(&shower) {
                     Some((count, _)) => count_parts.push(format!("C;{}", count)),
@@ -144,70 +322,251 @@ impl Session {
             ));
         }
 
-        Ok((count_csv_parts.join("\n"), distr_csv_parts.join("\n")))
+        count_csv_parts.join("\n")
+    }
+
+    pub fn get_csvs(&self) -> Result<(String, String), NoneError> {
+        let mut distr_csv_parts: Vec<String> = vec![];
+        distr_csv_parts
+            .push("DATE UT;START;END;SHOWER;-6;-5;-4;-3;-2;-1;0;1;2;3;4;5;6;7".to_owned());
+        for period in &self.periods {
+            let count_and_dist = period.get_count_and_distribution();
+            distr_csv_parts.push(period.get_distribution_csv(&count_and_dist)?);
+        }
+
+        Ok((self.count_csv(false), distr_csv_parts.join("\n")))
+    }
+
+    // The meteors counted per shower over all periods.
+    fn total_counts(&self) -> HashMap<Shower, u32> {
+        let mut totals = HashMap::new();
+        for period in &self.periods {
+            for (shower, (count, _)) in period.get_count_and_distribution() {
+                *totals.entry(shower).or_insert(0) += count;
+            }
+        }
+        totals
+    }
+
+    // The count CSV with the solar longitudes (J2000) of the start, middle and end of each period.
+    pub fn get_extended_count_csv(&self) -> String {
+        self.count_csv(true)
+    }
+
+    // One line per meteor, with its time and the input line it comes from ("-" if unknown),
+    // and its attributes.
+    pub fn get_meteor_csv(&self) -> String {
+        let mut lines: Vec<String> = vec![];
+        lines.push("DATE UT;TIME UT;SHOWER;MAG;LINE;TRAIN;COLOR;NOTE".to_owned());
+        for period in &self.periods {
+            for meteor in &period.meteors {
+                let (date, time) = match meteor.time {
+                    Some(time) => {
+                        let resolved = period.resolve(time);
+                        (
+                            resolved.date,
+                            resolved.time.to_shorthand_int_notation().to_string(),
+                        )
+                    }
+                    None => (period.date, "-".to_owned()),
+                };
+                lines.push(format!(
+                    "{};{};{};{:.1};{};{}",
+                    date,
+                    time,
+                    meteor.shower.to_imo_code(),
+                    f64::from(meteor.magnitude) / 10_f64,
+                    match meteor.line {
+                        Some(line) => line.to_string(),
+                        None => "-".to_owned(),
+                    },
+                    meteor.attributes.csv_fields()
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // One line per limiting magnitude checkpoint, with the method it was found with and the
+    // raw reading for SQM measurements.
+    pub fn get_lm_csv(&self) -> String {
+        let mut lines = vec!["DATE UT;TIME UT;PERIOD;Lm;METHOD;SQM".to_owned()];
+        for (i, period) in self.periods.iter().enumerate() {
+            let checkpoints = &period.checkpoints;
+            for (lm, time) in &checkpoints.limiting_magnitudes {
+                let resolved = period.resolve(*time);
+                lines.push(format!(
+                    "{};{};{};{:.2};{};{}",
+                    resolved.date,
+                    resolved.time.to_shorthand_int_notation(),
+                    i + 1,
+                    lm.value,
+                    lm.method.name(),
+                    match lm.method.sqm_reading() {
+                        Some(reading) => reading.to_string(),
+                        None => "".to_owned(),
+                    }
+                ));
+            }
+        }
+        lines.join("\n")
     }
 }
 
+// One line per session (observer) with the number of periods, the total Teff and the count
+// per shower, followed by a line with the totals of all sessions.
+pub fn get_summary_csv(sessions: &[Session]) -> String {
+    let mut showers: HashSet<Shower> = HashSet::new();
+    for session in sessions {
+        showers.extend(session.all_showers());
+    }
+    let mut showers: Vec<Shower> = showers.into_iter().collect();
+    showers.sort_by_key(|s| s.to_imo_code());
+
+    let mut lines = vec![format!(
+        "OBSERVER;PERIODS;Teff;{}",
+        showers
+            .iter()
+            .map(|s| s.to_imo_code())
+            .collect::<Vec<String>>()
+            .join(";")
+    )];
+    let mut total_counts: HashMap<Shower, u32> = HashMap::new();
+    let mut total_teff = 0_f64;
+    let mut total_periods = 0;
+    for session in sessions {
+        let counts = session.total_counts();
+        let teff: f64 = session.periods.iter().map(|p| p.teff).sum();
+        let counts_text: Vec<String> = showers
+            .iter()
+            .map(|s| match counts.get(s) {
+                Some(count) => count.to_string(),
+                None => "-".to_owned(),
+            })
+            .collect();
+        lines.push(format!(
+            "{};{};{:.4};{}",
+            csv_text(session.header.observer.as_deref().unwrap_or("")),
+            session.periods.len(),
+            teff,
+            counts_text.join(";")
+        ));
+        for (shower, count) in counts {
+            *total_counts.entry(shower).or_insert(0) += count;
+        }
+        total_teff += teff;
+        total_periods += session.periods.len();
+    }
+    let counts_text: Vec<String> = showers
+        .iter()
+        .map(|s| total_counts.get(s).copied().unwrap_or(0).to_string())
+        .collect();
+    lines.push(format!(
+        "TOTAL;{};{:.4};{}",
+        total_periods,
+        total_teff,
+        counts_text.join(";")
+    ));
+    lines.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::meteor::MeteorAttributes;
 
     #[test]
     fn test_get_count_and_distribution_1() {
         let period = Period {
             start_time: Timestamp { hour: 0, minute: 0 },
             end_time: Timestamp { hour: 0, minute: 0 },
-            date: "12 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
             teff: 0.0,
             limiting_magnitude: 0.0,
             field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
             cloud_factor: 1.0,
-            showers: vec![Shower::Perseids, Shower::Sporadic],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"SPO")],
             meteors: vec![
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 20,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: -5,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 40,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: -25,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 50,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
             ],
         };
         let cd = period.get_count_and_distribution();
-        let perseid_info = cd.get(&Shower::Perseids).unwrap();
+        let perseid_info = cd.get(&Shower(*b"PER")).unwrap();
         assert_eq!(perseid_info.0, 7);
         let perseid_distr_map = perseid_info.1.to_map();
         assert_eq!(*perseid_distr_map.get(&3).unwrap(), 50);
@@ -216,7 +575,7 @@ mod tests {
         assert_eq!(*perseid_distr_map.get(&-1).unwrap(), 5);
         assert_eq!(*perseid_distr_map.get(&-6).unwrap(), 0);
 
-        let sporadic_info = cd.get(&Shower::Sporadic).unwrap();
+        let sporadic_info = cd.get(&Shower(*b"SPO")).unwrap();
         assert_eq!(sporadic_info.0, 3);
         let sporadic_distr_map = sporadic_info.1.to_map();
         assert_eq!(*sporadic_distr_map.get(&-3).unwrap(), 5);
@@ -234,52 +593,88 @@ mod tests {
                 hour: 0,
                 minute: 30,
             },
-            date: "12 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
             teff: 0.0,
             limiting_magnitude: 0.0,
             field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
             cloud_factor: 1.0,
-            showers: vec![Shower::Perseids, Shower::Sporadic],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"SPO")],
             meteors: vec![
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 20,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: -5,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 40,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: -25,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 50,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
             ],
         };
@@ -294,36 +689,49 @@ mod tests {
         let period1 = Period {
             start_time: Timestamp { hour: 0, minute: 0 },
             end_time: Timestamp { hour: 0, minute: 0 },
-            date: "12 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
             teff: 0.0,
             limiting_magnitude: 0.0,
             field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
             cloud_factor: 1.0,
-            showers: vec![Shower::Perseids, Shower::Antihelion, Shower::Sporadic],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"ANT"), Shower(*b"SPO")],
             meteors: vec![],
         };
 
         let period2 = Period {
             start_time: Timestamp { hour: 0, minute: 0 },
             end_time: Timestamp { hour: 0, minute: 0 },
-            date: "12 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
             teff: 0.0,
             limiting_magnitude: 0.0,
             field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
             cloud_factor: 1.0,
-            showers: vec![Shower::KappaCygnids, Shower::Sporadic],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"KCG"), Shower(*b"SPO")],
             meteors: vec![],
         };
 
         let session = Session {
+            header: SessionHeader::default(),
             periods: vec![period1, period2],
         };
 
         let mut expected = HashSet::new();
-        expected.insert(Shower::Perseids);
-        expected.insert(Shower::Antihelion);
-        expected.insert(Shower::KappaCygnids);
-        expected.insert(Shower::Sporadic);
+        expected.insert(Shower(*b"PER"));
+        expected.insert(Shower(*b"ANT"));
+        expected.insert(Shower(*b"KCG"));
+        expected.insert(Shower(*b"SPO"));
         assert_eq!(session.all_showers(), expected);
     }
 
@@ -338,55 +746,91 @@ mod tests {
                 hour: 0,
                 minute: 30,
             },
-            date: "12 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
             teff: 1.0,
             limiting_magnitude: 5.52,
             field: Field {
                 ra: 336.0,
                 dec: 52.3,
             },
+            location: None,
             cloud_factor: 1.05,
-            showers: vec![Shower::Perseids, Shower::Sporadic, Shower::KappaCygnids],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"SPO"), Shower(*b"KCG")],
             meteors: vec![
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 20,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: -5,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 40,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: -25,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 50,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Perseids,
+                    shower: Shower(*b"PER"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
             ],
         };
@@ -397,32 +841,48 @@ mod tests {
                 minute: 30,
             },
             end_time: Timestamp { hour: 2, minute: 0 },
-            date: "13 Aug 2019".to_owned(),
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 13,
+            },
             teff: 0.5,
             limiting_magnitude: 5.91,
             field: Field {
                 ra: 298.0,
                 dec: 56.0,
             },
+            location: None,
             cloud_factor: 1.08,
-            showers: vec![Shower::Antihelion, Shower::Sporadic, Shower::KappaCygnids],
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"ANT"), Shower(*b"SPO"), Shower(*b"KCG")],
             meteors: vec![
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: -10,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
                 Meteor {
-                    shower: Shower::Sporadic,
+                    shower: Shower(*b"SPO"),
                     magnitude: 5,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
                 },
             ],
         };
 
         let session = Session {
+            header: SessionHeader::default(),
             periods: vec![period1, period2],
         };
         let (count_csv, distr_csv) = session.get_csvs().unwrap();
@@ -442,5 +902,190 @@ mod tests {
 13 Aug 2019;130;200;KCG;0;0;0;0;0;0;0;0;0;0;0;0;0;0
 13 Aug 2019;130;200;SPO;0;0;0;0;0;1;0.5;0.5;0;1;0;0;0;0"
         );
+
+        assert_eq!(
+            session.get_extended_count_csv(),
+            "DATE UT;START;END;Teff;RA;Dec;F;Lm;SOL START;SOL MID;SOL END;ANT;;KCG;;PER;;SPO
+12 Aug 2019;2330;30;1.0000;336;52.3;1.0500;5.5200;139.702;139.722;139.742;-;;C;0;C;7;C;3
+13 Aug 2019;130;200;0.5000;298;56;1.0800;5.9100;139.782;139.792;139.802;C;0;C;0;-;;C;3"
+        );
+
+        let session = Session {
+            header: SessionHeader {
+                observer: Some("DANTH".to_owned()),
+                site: None,
+            },
+            periods: session.periods,
+        };
+        assert!(session.get_extended_count_csv().starts_with(
+            "DATE UT;START;END;Teff;RA;Dec;F;Lm;OBSERVER;SITE;LAT;LON;ELEV;SOL START;SOL MID;SOL END;ANT;;KCG;;PER;;SPO
+12 Aug 2019;2330;30;1.0000;336;52.3;1.0500;5.5200;DANTH;;;;;139.702;"
+        ));
+        assert_eq!(session.get_csvs().unwrap().0, count_csv);
+    }
+
+    #[test]
+    fn test_meteor_csv() {
+        let period = Period {
+            start_time: Timestamp {
+                hour: 23,
+                minute: 30,
+            },
+            end_time: Timestamp {
+                hour: 0,
+                minute: 30,
+            },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
+            teff: 1.0,
+            limiting_magnitude: 6.5,
+            field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"SPO")],
+            meteors: vec![
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 35,
+                    time: Some(Timestamp {
+                        hour: 23,
+                        minute: 45,
+                    }),
+                    line: Some(12),
+                    attributes: MeteorAttributes {
+                        train: Some(3.0),
+                        color: Some("green".to_owned()),
+                        note: None,
+                    },
+                },
+                Meteor {
+                    shower: Shower(*b"SPO"),
+                    magnitude: -5,
+                    time: Some(Timestamp { hour: 0, minute: 5 }),
+                    line: Some(15),
+                    attributes: MeteorAttributes::default(),
+                },
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 20,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+            ],
+        };
+        let session = Session {
+            header: SessionHeader::default(),
+            periods: vec![period],
+        };
+        assert_eq!(
+            session.get_meteor_csv(),
+            "DATE UT;TIME UT;SHOWER;MAG;LINE;TRAIN;COLOR;NOTE
+12 Aug 2019;2345;PER;3.5;12;3;green;
+13 Aug 2019;5;SPO;-0.5;15;;;
+12 Aug 2019;-;PER;2.0;-;;;"
+        );
+
+        let mut period = session.periods[0].clone();
+        period.showers = vec![Shower(*b"KCG"), Shower(*b"SPO")];
+        period.meteors = vec![];
+        let other = Session {
+            header: SessionHeader {
+                observer: Some("BBBBB".to_owned()),
+                site: None,
+            },
+            periods: vec![period.clone(), period],
+        };
+        assert_eq!(
+            get_summary_csv(&[session, other]),
+            "OBSERVER;PERIODS;Teff;KCG;PER;SPO
+;1;1.0000;-;2;1
+BBBBB;2;2.0000;0;-;0
+TOTAL;3;3.0000;0;2;1"
+        );
+    }
+
+    #[test]
+    fn test_radiant_altitudes() {
+        let mut period = Period {
+            start_time: Timestamp {
+                hour: 23,
+                minute: 0,
+            },
+            end_time: Timestamp { hour: 1, minute: 0 },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
+            teff: 2.0,
+            limiting_magnitude: 6.0,
+            field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"ANT"), Shower(*b"SPO")],
+            meteors: vec![],
+        };
+        let catalog = Catalog::bundled();
+        assert!(period.radiant_altitudes(&catalog).is_empty());
+
+        period.location = Some(Location {
+            latitude: 51.0,
+            longitude: 4.0,
+            elevation: 10.0,
+        });
+        let altitudes = period.radiant_altitudes(&catalog);
+        assert_eq!(altitudes.len(), 1);
+        assert_eq!(altitudes[&Shower(*b"PER")].round(), 45.0);
+    }
+
+    #[test]
+    fn test_period_start_end() {
+        let period = Period {
+            start_time: Timestamp {
+                hour: 22,
+                minute: 0,
+            },
+            end_time: Timestamp { hour: 1, minute: 0 },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 31,
+            },
+            teff: 3.0,
+            limiting_magnitude: 6.0,
+            field: Field { ra: 0.0, dec: 0.0 },
+            location: None,
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![],
+            meteors: vec![],
+        };
+        assert_eq!(period.start().date.to_string(), "31 Aug 2019");
+        assert_eq!(period.end().date.to_string(), "1 Sep 2019");
+        assert_eq!(
+            period
+                .resolve(Timestamp {
+                    hour: 23,
+                    minute: 30
+                })
+                .date
+                .to_string(),
+            "31 Aug 2019"
+        );
+        assert_eq!(
+            period.midpoint_julian_day(),
+            period
+                .resolve(Timestamp {
+                    hour: 23,
+                    minute: 30
+                })
+                .julian_day()
+        );
     }
 }
@@ -0,0 +1,54 @@
+// Converts a sky quality meter reading (mag/arcsec²) to a naked-eye limiting magnitude:
+//
+//     Lm = A - 5 * log10(10^(B - SQM / 5) + 1)
+//
+// With the defaults (A = 7.93, B = 4.316), Lm goes to A for a perfectly dark sky and
+// drops by about one magnitude for each magnitude the sky gets brighter. Observers who
+// calibrated their meter against star counts can give their own constants.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SqmConversion {
+    pub a: f64,
+    pub b: f64,
+}
+
+impl Default for SqmConversion {
+    fn default() -> Self {
+        Self { a: 7.93, b: 4.316 }
+    }
+}
+
+impl SqmConversion {
+    pub fn limiting_magnitude(&self, reading: f64) -> f64 {
+        self.a - 5_f64 * (10_f64.powf(self.b - reading / 5_f64) + 1_f64).log10()
+    }
+}
+
+// Reads the constants as "A,B", like the --sqm-formula option takes them.
+impl std::str::FromStr for SqmConversion {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut split = s.splitn(2, ',');
+        let a = split.next().unwrap_or("").trim().parse()?;
+        let b = split.next().unwrap_or("").trim().parse()?;
+        Ok(Self { a, b })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqm_conversion() {
+        let conversion = SqmConversion::default();
+        assert!((conversion.limiting_magnitude(22.0) - 6.62).abs() < 0.01);
+        assert!((conversion.limiting_magnitude(21.35) - 6.31).abs() < 0.01);
+        assert!((conversion.limiting_magnitude(19.0) - 4.77).abs() < 0.01);
+        assert!(conversion.limiting_magnitude(21.0) < conversion.limiting_magnitude(21.5));
+
+        let custom: SqmConversion = "7.5, 4.3".parse().unwrap();
+        assert_eq!(custom, SqmConversion { a: 7.5, b: 4.3 });
+        assert!("7.5".parse::<SqmConversion>().is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::meteor::Shower;
+use crate::session::{Period, Session};
+use std::collections::BTreeMap;
+
+// Statistics of the magnitudes as they were logged, so half magnitudes are not split over
+// two classes like in the distribution. Magnitudes are in whole magnitudes here.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MagnitudeStats {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub brightest: f64,
+    // None for a single meteor.
+    pub standard_deviation: Option<f64>,
+    // The meteors of magnitude Lm - 1 or brighter.
+    pub bright_count: usize,
+}
+
+impl Period {
+    pub fn magnitude_stats(&self, shower: Shower) -> Option<MagnitudeStats> {
+        let mut magnitudes: Vec<f64> = self
+            .meteors
+            .iter()
+            .filter(|m| m.shower == shower)
+            .map(|m| f64::from(m.magnitude) / 10_f64)
+            .collect();
+        if magnitudes.is_empty() {
+            return None;
+        }
+        magnitudes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let count = magnitudes.len();
+        let mean = magnitudes.iter().sum::<f64>() / count as f64;
+        let median = if count % 2 == 1 {
+            magnitudes[count / 2]
+        } else {
+            (magnitudes[count / 2 - 1] + magnitudes[count / 2]) / 2_f64
+        };
+        let standard_deviation = if count > 1 {
+            let squares: f64 = magnitudes.iter().map(|m| (m - mean).powi(2)).sum();
+            Some((squares / (count - 1) as f64).sqrt())
+        } else {
+            None
+        };
+        Some(MagnitudeStats {
+            count,
+            mean,
+            median,
+            brightest: magnitudes[0],
+            standard_deviation,
+            bright_count: magnitudes
+                .iter()
+                .filter(|m| **m <= self.limiting_magnitude - 1_f64)
+                .count(),
+        })
+    }
+}
+
+impl Session {
+    // The magnitude statistics per period and shower. Showers without meteors in a period
+    // only get the count.
+    pub fn get_magnitude_csv(&self) -> String {
+        let mut lines =
+            vec!["DATE UT;START;END;SHOWER;Lm;N;MEAN;MEDIAN;BRIGHTEST;SD;N(Lm-1)".to_owned()];
+        for period in &self.periods {
+            let mut showers = period.showers.clone();
+            showers.sort_by_key(|s| s.to_imo_code());
+            for shower in showers {
+                let stats = match period.magnitude_stats(shower) {
+                    Some(s) => format!(
+                        "{};{:.2};{:.2};{:.1};{};{}",
+                        s.count,
+                        s.mean,
+                        s.median,
+                        s.brightest,
+                        match s.standard_deviation {
+                            Some(sd) => format!("{:.2}", sd),
+                            None => "-".to_owned(),
+                        },
+                        s.bright_count
+                    ),
+                    None => "0;-;-;-;-;0".to_owned(),
+                };
+                lines.push(format!(
+                    "{};{};{};{};{:.2};{}",
+                    period.date,
+                    period.start_time.to_shorthand_int_notation(),
+                    period.end_time.to_shorthand_int_notation(),
+                    shower.to_imo_code(),
+                    period.limiting_magnitude,
+                    stats
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // For every shower and magnitude, how many of the meteors left a train. Each shower ends
+    // with a line for all its magnitudes together.
+    pub fn get_train_csv(&self) -> String {
+        // (meteors, meteors with a train), by shower code and magnitude
+        let mut counts: BTreeMap<String, BTreeMap<i32, (u32, u32)>> = BTreeMap::new();
+        for period in &self.periods {
+            for meteor in &period.meteors {
+                let count = counts
+                    .entry(meteor.shower.to_imo_code())
+                    .or_default()
+                    .entry(meteor.magnitude)
+                    .or_insert((0, 0));
+                count.0 += 1;
+                if meteor.attributes.has_train() {
+                    count.1 += 1;
+                }
+            }
+        }
+
+        let line = |shower: &str, magnitude: &str, (meteors, trains): (u32, u32)| {
+            format!(
+                "{};{};{};{};{:.1}",
+                shower,
+                magnitude,
+                meteors,
+                trains,
+                f64::from(trains) / f64::from(meteors) * 100_f64
+            )
+        };
+        let mut lines = vec!["SHOWER;MAG;METEORS;TRAINS;PERCENTAGE".to_owned()];
+        for (shower, by_magnitude) in counts {
+            let mut total = (0, 0);
+            for (magnitude, count) in by_magnitude {
+                lines.push(line(
+                    &shower,
+                    &format!("{:.1}", f64::from(magnitude) / 10_f64),
+                    count,
+                ));
+                total = (total.0 + count.0, total.1 + count.1);
+            }
+            lines.push(line(&shower, "ALL", total));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::meteor::Shower;
+
+    fn round(a: f64) -> f64 {
+        (a * 1000_f64).round() / 1000_f64
+    }
+
+    #[test]
+    fn test_magnitude_stats() {
+        let session = Interpreter::test_session(
+            "2100
+             period_start
+             date(\"12 Aug 2019\")
+             showers(PER, KCG, SPO)
+             fieldC(336, 52.3)
+             clouds(0)
+             areas(area14(10))
+             per(1.5)
+             per(3)
+             per(-2)
+             per(5)
+             kcg(2)
+             period_end << 2200",
+        );
+        let period = &session.periods[0];
+        let stats = period.magnitude_stats(Shower(*b"PER")).unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean, 1.875);
+        assert_eq!(stats.median, 2.25);
+        assert_eq!(stats.brightest, -2.0);
+        assert_eq!(round(stats.standard_deviation.unwrap()), 2.955);
+        assert_eq!(period.magnitude_stats(Shower::SPORADIC), None);
+        assert_eq!(
+            period
+                .magnitude_stats(Shower(*b"KCG"))
+                .unwrap()
+                .standard_deviation,
+            None
+        );
+
+        // Lm is 5.58 for 10 stars in area 14, so magnitude 5 is too faint.
+        assert_eq!(
+            session.get_magnitude_csv(),
+            "DATE UT;START;END;SHOWER;Lm;N;MEAN;MEDIAN;BRIGHTEST;SD;N(Lm-1)
+12 Aug 2019;2100;2200;KCG;5.58;1;2.00;2.00;2.0;-;1
+12 Aug 2019;2100;2200;PER;5.58;4;1.88;2.25;-2.0;2.95;3
+12 Aug 2019;2100;2200;SPO;5.58;0;-;-;-;-;0"
+        );
+    }
+
+    #[test]
+    fn test_train_csv() {
+        let session = Interpreter::test_session(
+            "2100
+             period_start
+             date(\"12 Aug 2019\")
+             showers(PER, SPO)
+             fieldC(336, 52.3)
+             clouds(0)
+             areas(area14(10))
+             per(1, {train=3})
+             per(1)
+             per(-2, {train=10, color=\"green\"})
+             spo(3)
+             per(1, {train=0})
+             period_end << 2200",
+        );
+        assert_eq!(
+            session.get_train_csv(),
+            "SHOWER;MAG;METEORS;TRAINS;PERCENTAGE
+PER;-2.0;1;1;100.0
+PER;1.0;3;1;33.3
+PER;ALL;4;2;50.0
+SPO;3.0;1;0;0.0
+SPO;ALL;1;0;0.0"
+        );
+    }
+}
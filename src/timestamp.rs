@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug, PartialEq)]
+use crate::astro;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Timestamp {
     pub hour: u32,
     pub minute: u32,
@@ -28,6 +30,15 @@ impl Timestamp {
     pub fn to_shorthand_int_notation(self) -> u32 {
         self.hour * 100 + self.minute
     }
+
+    // The time shown by the clock the given number of minutes later.
+    pub fn plus_minutes(self, minutes: u32) -> Self {
+        let total = (self.hour * 60 + self.minute + minutes) % (24 * 60);
+        Self {
+            hour: total / 60,
+            minute: total % 60,
+        }
+    }
 }
 
 pub fn effective_time_minutes(
@@ -48,6 +59,123 @@ pub fn effective_time_minutes(
     Some(minutes)
 }
 
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub fn parse_month(name: &str) -> Option<u32> {
+    MONTHS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(name))
+        .map(|m| m as u32 + 1)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    // Parses the IMO date notations "11 Aug 2019" and "Aug 11 2019".
+    pub fn parse(date: &str) -> Option<Self> {
+        let parts: Vec<&str> = date.split_whitespace().collect();
+        if parts.len() != 3 {
+            return None;
+        }
+        let (day, month) = match (parse_month(parts[0]), parse_month(parts[1])) {
+            (None, Some(month)) => (parts[0].parse::<u32>().ok()?, month),
+            (Some(month), None) => (parts[1].parse::<u32>().ok()?, month),
+            _ => return None,
+        };
+        let year = parts[2].parse::<i32>().ok()?;
+        let date = Self { year, month, day };
+        if day == 0 || day > date.days_in_month() {
+            return None;
+        }
+        Some(date)
+    }
+
+    fn days_in_month(self) -> u32 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    pub fn next_day(self) -> Self {
+        if self.day < self.days_in_month() {
+            Self {
+                day: self.day + 1,
+                ..self
+            }
+        } else if self.month < 12 {
+            Self {
+                month: self.month + 1,
+                day: 1,
+                ..self
+            }
+        } else {
+            Self {
+                year: self.year + 1,
+                month: 1,
+                day: 1,
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.day,
+            MONTHS[(self.month - 1) as usize],
+            self.year
+        )
+    }
+}
+
+// A moment in UTC, with minute precision.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Timestamp,
+}
+
+impl DateTime {
+    // The first moment at or after this one at which the clock shows the given time,
+    // so the date advances when the clock wraps past midnight.
+    pub fn resolve(self, time: Timestamp) -> Self {
+        let date = if time < self.time {
+            self.date.next_day()
+        } else {
+            self.date
+        };
+        Self { date, time }
+    }
+
+    pub fn julian_day(self) -> f64 {
+        astro::julian_day(
+            self.date.year,
+            self.date.month,
+            self.date.day,
+            self.time.hour,
+            self.time.minute,
+        )
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
 #[allow(clippy::suspicious_arithmetic_impl)]
 impl std::ops::Sub for Timestamp {
     type Output = u32;
@@ -112,6 +240,83 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_parse_date_1() {
+        let expected = Some(Date {
+            year: 2019,
+            month: 8,
+            day: 11,
+        });
+        assert_eq!(Date::parse("11 Aug 2019"), expected);
+        assert_eq!(Date::parse("Aug 11 2019"), expected);
+        assert_eq!(
+            Date::parse("29 feb 2020"),
+            Some(Date {
+                year: 2020,
+                month: 2,
+                day: 29
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_parse_date_2() {
+        assert_eq!(Date::parse("2019-08-11"), None);
+        assert_eq!(Date::parse("Aug Aug 2019"), None);
+        assert_eq!(Date::parse("32 Aug 2019"), None);
+        assert_eq!(Date::parse("31 Sep 2019"), None);
+        assert_eq!(Date::parse("29 Feb 2019"), None);
+        assert_eq!(Date::parse("11 Aug"), None);
+    }
+
+    #[test]
+    pub fn test_date_display() {
+        assert_eq!(
+            Date::parse("Aug 11 2019").unwrap().to_string(),
+            "11 Aug 2019"
+        );
+        assert_eq!(Timestamp { hour: 0, minute: 7 }.to_string(), "00:07");
+        assert_eq!(
+            Timestamp::from_shorthand_int_notation(2350).plus_minutes(25),
+            Timestamp::from_shorthand_int_notation(15)
+        );
+    }
+
+    #[test]
+    pub fn test_next_day() {
+        let next = |s| Date::parse(s).unwrap().next_day().to_string();
+        assert_eq!(next("11 Aug 2019"), "12 Aug 2019");
+        assert_eq!(next("31 Aug 2019"), "1 Sep 2019");
+        assert_eq!(next("28 Feb 2019"), "1 Mar 2019");
+        assert_eq!(next("28 Feb 2020"), "29 Feb 2020");
+        assert_eq!(next("31 Dec 2019"), "1 Jan 2020");
+    }
+
+    #[test]
+    pub fn test_resolve() {
+        let start = DateTime {
+            date: Date::parse("31 Dec 2019").unwrap(),
+            time: Timestamp {
+                hour: 22,
+                minute: 10,
+            },
+        };
+        let same_day = start.resolve(Timestamp {
+            hour: 23,
+            minute: 59,
+        });
+        assert_eq!(same_day.date, start.date);
+        assert_eq!(start.resolve(start.time), start);
+
+        let next_day = same_day.resolve(Timestamp { hour: 0, minute: 5 });
+        assert_eq!(next_day.date.to_string(), "1 Jan 2020");
+        assert!(next_day > same_day);
+        assert_eq!(
+            ((next_day.julian_day() - start.julian_day()) * 1440.0).round(),
+            115.0
+        );
+    }
+
     #[test]
     pub fn test_sub_1() {
         let t1 = Timestamp {
@@ -0,0 +1,321 @@
+use crate::catalog::Catalog;
+use crate::meteor::Shower;
+use crate::session::{Period, Session};
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Zhr {
+    pub count: u32,
+    pub zhr: f64,
+    pub error: f64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ZhrParameters {
+    // Overrides the population indices of the catalog.
+    pub population_index: Option<f64>,
+    // Population indices per shower, fitted on the magnitude distribution. These come after
+    // `population_index`, but before the catalog.
+    pub population_indices: HashMap<Shower, f64>,
+    // Used for periods for which the radiant altitude can't be computed.
+    pub radiant_altitude: Option<f64>,
+}
+
+// IMO handbook: ZHR = N * F * r^(6.5 - Lm) / (Teff * sin(h)), with a relative
+// error of 1 / sqrt(N) because meteor counts follow a Poisson distribution.
+pub fn zhr(
+    count: u32,
+    teff: f64,
+    cloud_factor: f64,
+    limiting_magnitude: f64,
+    population_index: f64,
+    radiant_altitude: f64,
+) -> Option<Zhr> {
+    if teff <= 0_f64 || radiant_altitude <= 0_f64 {
+        return None;
+    }
+
+    let correction = cloud_factor * population_index.powf(6.5 - limiting_magnitude)
+        / (teff * radiant_altitude.to_radians().sin());
+    Some(Zhr {
+        count,
+        zhr: f64::from(count) * correction,
+        error: f64::from(count).sqrt() * correction,
+    })
+}
+
+impl Period {
+    // Population index and radiant altitude to use for each shower of the period.
+    fn zhr_inputs(
+        &self,
+        parameters: &ZhrParameters,
+        catalog: &Catalog,
+    ) -> HashMap<Shower, (Option<f64>, Option<f64>)> {
+        let altitudes = self.radiant_altitudes(catalog);
+        self.showers
+            .iter()
+            .filter(|shower| **shower != Shower::SPORADIC)
+            .map(|shower| {
+                let population_index = parameters
+                    .population_index
+                    .or_else(|| parameters.population_indices.get(shower).copied())
+                    .or_else(|| catalog.get(*shower).and_then(|info| info.population_index));
+                let altitude = altitudes
+                    .get(shower)
+                    .copied()
+                    .or(parameters.radiant_altitude);
+                (*shower, (population_index, altitude))
+            })
+            .collect()
+    }
+
+    pub fn get_zhrs(
+        &self,
+        parameters: &ZhrParameters,
+        catalog: &Catalog,
+    ) -> HashMap<Shower, Option<Zhr>> {
+        let counts = self.get_count_and_distribution();
+        self.zhr_inputs(parameters, catalog)
+            .into_iter()
+            .map(|(shower, inputs)| {
+                let z = match inputs {
+                    (Some(population_index), Some(altitude)) => zhr(
+                        counts[&shower].0,
+                        self.teff,
+                        self.cloud_factor,
+                        self.limiting_magnitude,
+                        population_index,
+                        altitude,
+                    ),
+                    _ => None,
+                };
+                (shower, z)
+            })
+            .collect()
+    }
+}
+
+impl Session {
+    pub fn get_zhr_csv(&self, parameters: &ZhrParameters, catalog: &Catalog) -> String {
+        let mut lines: Vec<String> = vec![];
+        lines.push("DATE UT;START;END;SHOWER;N;Teff;F;Lm;r;h;ZHR;ERR".to_owned());
+
+        let or_dash = |value: Option<f64>, precision: usize| match value {
+            Some(v) => format!("{:.*}", precision, v),
+            None => "-".to_owned(),
+        };
+
+        for period in &self.periods {
+            let counts = period.get_count_and_distribution();
+            let inputs = period.zhr_inputs(parameters, catalog);
+            let zhrs = period.get_zhrs(parameters, catalog);
+            let mut showers: Vec<&Shower> = zhrs.keys().collect();
+            showers.sort_by_key(|s| s.to_imo_code());
+
+            for shower in showers {
+                let (population_index, altitude) = inputs[shower];
+                let zhr = zhrs[shower];
+                lines.push(format!(
+                    "{};{};{};{};{};{:.4};{:.4};{:.4};{};{};{};{}",
+                    period.date,
+                    period.start_time.to_shorthand_int_notation(),
+                    period.end_time.to_shorthand_int_notation(),
+                    shower.to_imo_code(),
+                    counts[shower].0,
+                    period.teff,
+                    period.cloud_factor,
+                    period.limiting_magnitude,
+                    or_dash(population_index, 2),
+                    or_dash(altitude, 1),
+                    or_dash(zhr.map(|z| z.zhr), 1),
+                    or_dash(zhr.map(|z| z.error), 1)
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Field;
+    use crate::location::Location;
+    use crate::meteor::{Meteor, MeteorAttributes};
+    use crate::session::{Checkpoints, SessionHeader};
+    use crate::timestamp::{Date, Timestamp};
+
+    fn round(a: f64) -> f64 {
+        (a * 100_f64).round() / 100_f64
+    }
+
+    #[test]
+    fn test_zhr_1() {
+        let z = zhr(25, 1.0, 1.0, 6.5, 2.2, 90.0).unwrap();
+        assert_eq!(z.count, 25);
+        assert_eq!(round(z.zhr), 25.0);
+        assert_eq!(round(z.error), 5.0);
+    }
+
+    #[test]
+    fn test_zhr_2() {
+        let z = zhr(16, 0.5, 1.05, 5.5, 2.0, 30.0).unwrap();
+        assert_eq!(round(z.zhr), 134.4);
+        assert_eq!(round(z.error), 33.6);
+    }
+
+    #[test]
+    fn test_zhr_3() {
+        assert_eq!(zhr(10, 1.0, 1.0, 6.0, 2.5, -5.0), None);
+        assert_eq!(zhr(10, 0.0, 1.0, 6.0, 2.5, 45.0), None);
+    }
+
+    #[test]
+    fn test_zhr_4() {
+        let z = zhr(0, 1.0, 1.0, 6.0, 2.5, 45.0).unwrap();
+        assert_eq!(z.zhr, 0.0);
+        assert_eq!(z.error, 0.0);
+    }
+
+    #[test]
+    fn test_session_zhr_csv() {
+        let period = Period {
+            start_time: Timestamp {
+                hour: 23,
+                minute: 0,
+            },
+            end_time: Timestamp { hour: 0, minute: 0 },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 12,
+            },
+            teff: 1.0,
+            limiting_magnitude: 6.5,
+            field: Field {
+                ra: 336.0,
+                dec: 52.3,
+            },
+            location: None,
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"KCG"), Shower(*b"SPO")],
+            meteors: vec![
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 30,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 20,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 25,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+                Meteor {
+                    shower: Shower(*b"PER"),
+                    magnitude: 40,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+                Meteor {
+                    shower: Shower(*b"SPO"),
+                    magnitude: 40,
+                    time: None,
+                    line: None,
+                    attributes: MeteorAttributes::default(),
+                },
+            ],
+        };
+        let session = Session {
+            header: SessionHeader::default(),
+            periods: vec![period],
+        };
+        let parameters = ZhrParameters {
+            population_index: Some(2.2),
+            population_indices: HashMap::new(),
+            radiant_altitude: Some(30.0),
+        };
+        assert_eq!(
+            session.get_zhr_csv(&parameters, &Catalog::bundled()),
+            "DATE UT;START;END;SHOWER;N;Teff;F;Lm;r;h;ZHR;ERR
+12 Aug 2019;2300;0;KCG;0;1.0000;1.0000;6.5000;2.20;30.0;0.0;0.0
+12 Aug 2019;2300;0;PER;4;1.0000;1.0000;6.5000;2.20;30.0;8.0;4.0"
+        );
+    }
+
+    #[test]
+    fn test_period_zhr_location() {
+        let period = Period {
+            start_time: Timestamp { hour: 1, minute: 0 },
+            end_time: Timestamp { hour: 2, minute: 0 },
+            date: Date {
+                year: 2019,
+                month: 8,
+                day: 13,
+            },
+            teff: 1.0,
+            limiting_magnitude: 6.5,
+            field: Field {
+                ra: 336.0,
+                dec: 52.3,
+            },
+            location: Some(Location {
+                latitude: -45.0,
+                longitude: 170.0,
+                elevation: 0.0,
+            }),
+            cloud_factor: 1.0,
+            checkpoints: Checkpoints::default(),
+            showers: vec![Shower(*b"PER"), Shower(*b"ANT")],
+            meteors: vec![Meteor {
+                shower: Shower(*b"ANT"),
+                magnitude: 30,
+                time: None,
+                line: None,
+                attributes: MeteorAttributes::default(),
+            }],
+        };
+        let catalog = Catalog::bundled();
+        let parameters = ZhrParameters {
+            population_index: None,
+            population_indices: HashMap::new(),
+            radiant_altitude: None,
+        };
+        assert!(period.radiant_altitudes(&catalog)[&Shower(*b"PER")] < 0.0);
+        let zhrs = period.get_zhrs(&parameters, &catalog);
+        assert_eq!(zhrs[&Shower(*b"PER")], None);
+        assert_eq!(zhrs[&Shower(*b"ANT")], None);
+
+        let parameters = ZhrParameters {
+            population_index: None,
+            population_indices: HashMap::new(),
+            radiant_altitude: Some(90.0),
+        };
+        let zhrs = period.get_zhrs(&parameters, &catalog);
+        assert_eq!(zhrs[&Shower(*b"PER")], None);
+        // r = 3.0 for the antihelion source in the bundled catalog
+        assert_eq!(round(zhrs[&Shower(*b"ANT")].unwrap().zhr), 1.0);
+
+        // A fitted population index comes before the catalog.
+        let mut parameters = parameters;
+        parameters.population_indices.insert(Shower(*b"ANT"), 2.0);
+        let inputs = period.zhr_inputs(&parameters, &catalog);
+        assert_eq!(inputs[&Shower(*b"ANT")].0, Some(2.0));
+        parameters.population_index = Some(2.5);
+        let inputs = period.zhr_inputs(&parameters, &catalog);
+        assert_eq!(inputs[&Shower(*b"ANT")].0, Some(2.5));
+    }
+}